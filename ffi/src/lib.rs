@@ -0,0 +1,5 @@
+//! Builds the C interface of `avl` as `libavl_ffi.a` and `libavl_ffi.so`. It lives
+//! in its own package so that `avl` itself stays an rlib that `no_std`
+//! users can link.
+
+pub use avl::ffi::*;
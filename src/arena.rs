@@ -0,0 +1,646 @@
+use alloc::vec::Vec;
+use core::cmp;
+
+use crate::{Direction, Height, MemoryUsage};
+
+const NIL: u32 = u32::MAX;
+
+pub struct ArenaAvlTree<V> {
+    slots: Vec<Slot<V>>,
+    root: u32,
+    free: u32,
+    len: usize,
+    next_stamp: u64,
+}
+
+/// Refers to one node of an [`ArenaAvlTree`] for as long as that node
+/// stays in the tree, regardless of other inserts and removes.
+///
+/// Every node gets a stamp that is never reused, so a handle to a removed
+/// node does not resolve to whichever node later takes its slot. Only
+/// [`ArenaAvlTree::compact`] moves nodes, and it reports the new handle of
+/// every node it moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeHandle {
+    index: u32,
+    stamp: u64,
+}
+
+enum Slot<V> {
+    Occupied(ArenaNode<V>),
+    Vacant { next_free: u32 },
+}
+
+struct ArenaNode<V> {
+    val: V,
+    height: Height,
+    left: u32,
+    right: u32,
+    stamp: u64,
+}
+
+impl<V> Default for ArenaAvlTree<V> {
+    fn default() -> Self {
+        ArenaAvlTree {
+            slots: Vec::new(),
+            root: NIL,
+            free: NIL,
+            len: 0,
+            next_stamp: 0,
+        }
+    }
+}
+
+impl<V: Ord> ArenaAvlTree<V> {
+    pub fn new() -> ArenaAvlTree<V> {
+        ArenaAvlTree::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> ArenaAvlTree<V> {
+        ArenaAvlTree {
+            slots: Vec::with_capacity(capacity),
+            ..ArenaAvlTree::default()
+        }
+    }
+
+    /// Makes room for `additional` more values, counting vacant slots.
+    pub fn reserve(&mut self, additional: usize) {
+        let vacant = self.slots.len() - self.len;
+        self.slots.reserve(additional.saturating_sub(vacant));
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+
+    /// Releases the vacant slots after the last live node and the memory
+    /// behind them. Live nodes stay where they are, so every handle stays
+    /// valid; [`compact`](ArenaAvlTree::compact) also fills the vacant
+    /// slots between them.
+    pub fn shrink_to_fit(&mut self) {
+        let live = self
+            .slots
+            .iter()
+            .rposition(|slot| matches!(slot, Slot::Occupied(_)))
+            .map_or(0, |last| last + 1);
+        self.slots.truncate(live);
+
+        // Rebuild the free list from the remaining vacant slots, lowest
+        // first, so that new nodes fill the front of the arena.
+        self.free = NIL;
+        for (idx, slot) in self.slots.iter_mut().enumerate().rev() {
+            if let Slot::Vacant { next_free } = slot {
+                *next_free = self.free;
+                self.free = idx as u32;
+            }
+        }
+        self.slots.shrink_to_fit();
+    }
+
+    /// Moves the live nodes into a dense prefix of the slots, in their
+    /// current order, and releases the memory behind them. Handles to the
+    /// moved nodes become invalid; `moved` is called with the old and the
+    /// new handle of each, so that stored handles can be updated.
+    pub fn compact<F: FnMut(NodeHandle, NodeHandle)>(&mut self, mut moved: F) {
+        let mut remap = Vec::with_capacity(self.slots.len());
+        let mut next = 0;
+        for (idx, slot) in self.slots.iter().enumerate() {
+            remap.push(match slot {
+                Slot::Occupied(node) => {
+                    if idx as u32 != next {
+                        moved(
+                            NodeHandle {
+                                index: idx as u32,
+                                stamp: node.stamp,
+                            },
+                            NodeHandle {
+                                index: next,
+                                stamp: node.stamp,
+                            },
+                        );
+                    }
+                    next += 1;
+                    next - 1
+                }
+                Slot::Vacant { .. } => NIL,
+            });
+        }
+
+        self.slots.retain(|slot| matches!(slot, Slot::Occupied(_)));
+        let relink = |idx: u32| if idx == NIL { NIL } else { remap[idx as usize] };
+        for slot in &mut self.slots {
+            if let Slot::Occupied(node) = slot {
+                node.left = relink(node.left);
+                node.right = relink(node.right);
+            }
+        }
+        self.root = relink(self.root);
+        self.free = NIL;
+        self.slots.shrink_to_fit();
+    }
+
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.memory_usage_with(|_| 0)
+    }
+
+    pub fn memory_usage_with<F: FnMut(&V) -> usize>(&self, mut value_heap_bytes: F) -> MemoryUsage {
+        let slot_size = core::mem::size_of::<Slot<V>>();
+
+        MemoryUsage {
+            nodes: self.len,
+            node_bytes: self.len * slot_size,
+            spare_bytes: (self.slots.capacity() - self.len) * slot_size,
+            value_heap_bytes: self
+                .slots
+                .iter()
+                .map(|slot| match slot {
+                    Slot::Occupied(node) => value_heap_bytes(&node.val),
+                    Slot::Vacant { .. } => 0,
+                })
+                .sum(),
+        }
+    }
+
+    pub fn min(&self) -> Option<&V> {
+        self.extreme(Direction::Left)
+    }
+
+    pub fn max(&self) -> Option<&V> {
+        self.extreme(Direction::Right)
+    }
+
+    /// Returns a handle to the inserted value, or to the equal value
+    /// already in the tree.
+    pub fn insert(&mut self, value: V) -> NodeHandle {
+        let mut path = Vec::new();
+        let mut cur = self.root;
+
+        while cur != NIL {
+            let node = self.node(cur);
+            match value.cmp(&node.val) {
+                cmp::Ordering::Less => {
+                    path.push((cur, Direction::Left));
+                    cur = node.left;
+                }
+                cmp::Ordering::Greater => {
+                    path.push((cur, Direction::Right));
+                    cur = node.right;
+                }
+                cmp::Ordering::Equal => return self.handle(cur),
+            }
+        }
+
+        let new = self.alloc(value);
+        self.root = self.unwind(path, new);
+        self.len += 1;
+        self.handle(new)
+    }
+
+    pub fn remove(&mut self, value: &V) -> bool {
+        let (path, cur) = self.search(value);
+        if cur == NIL {
+            return false;
+        }
+
+        self.remove_at(path, cur);
+        true
+    }
+
+    pub fn find(&self, value: &V) -> Option<NodeHandle> {
+        let (_, cur) = self.search(value);
+        (cur != NIL).then(|| self.handle(cur))
+    }
+
+    /// Removes the node behind `handle`, if it is still in the tree.
+    pub fn remove_handle(&mut self, handle: NodeHandle) -> Option<V> {
+        self.get(handle)?;
+        let (path, cur) = self.search(&self.node(handle.index).val);
+        Some(self.remove_at(path, cur))
+    }
+
+    /// The search path to `value` and the index it was found at, or `NIL`.
+    fn search(&self, value: &V) -> (Vec<(u32, Direction)>, u32) {
+        let mut path = Vec::new();
+        let mut cur = self.root;
+
+        while cur != NIL {
+            let node = self.node(cur);
+            match value.cmp(&node.val) {
+                cmp::Ordering::Less => {
+                    path.push((cur, Direction::Left));
+                    cur = node.left;
+                }
+                cmp::Ordering::Greater => {
+                    path.push((cur, Direction::Right));
+                    cur = node.right;
+                }
+                cmp::Ordering::Equal => break,
+            }
+        }
+
+        (path, cur)
+    }
+
+    fn remove_at(&mut self, path: Vec<(u32, Direction)>, cur: u32) -> V {
+        let (left, right) = {
+            let node = self.node(cur);
+            (node.left, node.right)
+        };
+        let replacement = if left == NIL {
+            right
+        } else if right == NIL {
+            left
+        } else {
+            let (min, rest) = self.take_min(right);
+            let min_node = self.node_mut(min);
+            min_node.left = left;
+            min_node.right = rest;
+            self.update_height(min);
+            self.rebalance(min)
+        };
+
+        self.root = self.unwind(path, replacement);
+        self.len -= 1;
+        self.release(cur)
+    }
+
+    pub fn contains(&self, value: &V) -> bool {
+        let mut cur = self.root;
+
+        while cur != NIL {
+            let node = self.node(cur);
+            match value.cmp(&node.val) {
+                cmp::Ordering::Less => cur = node.left,
+                cmp::Ordering::Greater => cur = node.right,
+                cmp::Ordering::Equal => return true,
+            }
+        }
+
+        false
+    }
+
+    fn extreme(&self, direction: Direction) -> Option<&V> {
+        if self.root == NIL {
+            return None;
+        }
+
+        let mut node = self.node(self.root);
+        loop {
+            let next = match direction {
+                Direction::Left => node.left,
+                Direction::Right => node.right,
+            };
+            if next == NIL {
+                return Some(&node.val);
+            }
+            node = self.node(next);
+        }
+    }
+
+    fn take_min(&mut self, idx: u32) -> (u32, u32) {
+        let mut path = Vec::new();
+        let mut cur = idx;
+
+        loop {
+            let left = self.node(cur).left;
+            if left == NIL {
+                break;
+            }
+            path.push((cur, Direction::Left));
+            cur = left;
+        }
+
+        let rest = self.node(cur).right;
+        (cur, self.unwind(path, rest))
+    }
+
+    fn unwind(&mut self, path: Vec<(u32, Direction)>, mut child: u32) -> u32 {
+        let top = match path.first() {
+            Some(&(idx, _)) => idx,
+            None => return child,
+        };
+
+        for (idx, direction) in path.into_iter().rev() {
+            let node = self.node_mut(idx);
+            match direction {
+                Direction::Left => node.left = child,
+                Direction::Right => node.right = child,
+            }
+
+            let old_height = node.height;
+            self.update_height(idx);
+            child = self.rebalance(idx);
+
+            if child == idx && self.node(idx).height == old_height {
+                return top;
+            }
+        }
+
+        child
+    }
+}
+
+impl<V> ArenaAvlTree<V> {
+    /// The value behind `handle`, in O(1).
+    pub fn get(&self, handle: NodeHandle) -> Option<&V> {
+        match self.slots.get(handle.index as usize) {
+            Some(Slot::Occupied(node)) if node.stamp == handle.stamp => Some(&node.val),
+            _ => None,
+        }
+    }
+
+    /// Mutable access to the value behind `handle`, in O(1). The value must
+    /// keep its place in the order; changing it so that it compares
+    /// differently to its neighbours makes later searches miss values.
+    pub fn get_mut(&mut self, handle: NodeHandle) -> Option<&mut V> {
+        match self.slots.get_mut(handle.index as usize) {
+            Some(Slot::Occupied(node)) if node.stamp == handle.stamp => Some(&mut node.val),
+            _ => None,
+        }
+    }
+
+    fn handle(&self, idx: u32) -> NodeHandle {
+        NodeHandle {
+            index: idx,
+            stamp: self.node(idx).stamp,
+        }
+    }
+
+    fn node(&self, idx: u32) -> &ArenaNode<V> {
+        match self.slots[idx as usize] {
+            Slot::Occupied(ref node) => node,
+            Slot::Vacant { .. } => unreachable!("arena: link to vacant slot {}", idx),
+        }
+    }
+
+    fn node_mut(&mut self, idx: u32) -> &mut ArenaNode<V> {
+        match self.slots[idx as usize] {
+            Slot::Occupied(ref mut node) => node,
+            Slot::Vacant { .. } => unreachable!("arena: link to vacant slot {}", idx),
+        }
+    }
+
+    fn alloc(&mut self, value: V) -> u32 {
+        let node = ArenaNode {
+            val: value,
+            height: 1,
+            left: NIL,
+            right: NIL,
+            stamp: self.next_stamp,
+        };
+        self.next_stamp += 1;
+
+        if self.free != NIL {
+            let idx = self.free;
+            match core::mem::replace(&mut self.slots[idx as usize], Slot::Occupied(node)) {
+                Slot::Vacant { next_free } => self.free = next_free,
+                Slot::Occupied(_) => unreachable!("arena: free list points to occupied slot"),
+            }
+            idx
+        } else {
+            let idx = u32::try_from(self.slots.len())
+                .ok()
+                .filter(|&idx| idx != NIL)
+                .expect("arena: too many nodes");
+            self.slots.push(Slot::Occupied(node));
+            idx
+        }
+    }
+
+    fn release(&mut self, idx: u32) -> V {
+        let vacant = Slot::Vacant {
+            next_free: self.free,
+        };
+        self.free = idx;
+
+        match core::mem::replace(&mut self.slots[idx as usize], vacant) {
+            Slot::Occupied(node) => node.val,
+            Slot::Vacant { .. } => unreachable!("arena: released vacant slot {}", idx),
+        }
+    }
+
+    fn height(&self, idx: u32) -> Height {
+        if idx == NIL {
+            0
+        } else {
+            self.node(idx).height
+        }
+    }
+
+    fn update_height(&mut self, idx: u32) {
+        let node = self.node(idx);
+        let height = 1 + cmp::max(self.height(node.left), self.height(node.right));
+        self.node_mut(idx).height = height;
+    }
+
+    fn get_balance(&self, idx: u32) -> isize {
+        let node = self.node(idx);
+        self.height(node.left) as isize - self.height(node.right) as isize
+    }
+
+    fn rotate_left(&mut self, x: u32) -> u32 {
+        let y = self.node(x).right;
+        let t2 = self.node(y).left;
+
+        self.node_mut(x).right = t2;
+        self.update_height(x);
+        self.node_mut(y).left = x;
+        self.update_height(y);
+
+        y
+    }
+
+    fn rotate_right(&mut self, y: u32) -> u32 {
+        let x = self.node(y).left;
+        let t2 = self.node(x).right;
+
+        self.node_mut(y).left = t2;
+        self.update_height(y);
+        self.node_mut(x).right = y;
+        self.update_height(x);
+
+        x
+    }
+
+    fn rebalance(&mut self, idx: u32) -> u32 {
+        match self.get_balance(idx) {
+            2.. => {
+                let left = self.node(idx).left;
+                if self.get_balance(left) < 0 {
+                    let left = self.rotate_left(left);
+                    self.node_mut(idx).left = left;
+                }
+                self.rotate_right(idx)
+            }
+            ..-1 => {
+                let right = self.node(idx).right;
+                if self.get_balance(right) > 0 {
+                    let right = self.rotate_right(right);
+                    self.node_mut(idx).right = right;
+                }
+                self.rotate_left(idx)
+            }
+            -1..=1 => idx,
+        }
+    }
+}
+
+impl<V: Ord> IntoIterator for ArenaAvlTree<V> {
+    type Item = V;
+    type IntoIter = <Vec<V> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let mut order = Vec::with_capacity(self.len);
+        let mut stack = Vec::new();
+        let mut cur = self.root;
+
+        while cur != NIL || !stack.is_empty() {
+            while cur != NIL {
+                stack.push(cur);
+                cur = self.node(cur).left;
+            }
+            if let Some(idx) = stack.pop() {
+                order.push(idx);
+                cur = self.node(idx).right;
+            }
+        }
+
+        let mut values: Vec<Option<V>> = self
+            .slots
+            .into_iter()
+            .map(|slot| match slot {
+                Slot::Occupied(node) => Some(node.val),
+                Slot::Vacant { .. } => None,
+            })
+            .collect();
+
+        order
+            .into_iter()
+            .map(|idx| {
+                values[idx as usize]
+                    .take()
+                    .expect("arena: node visited twice")
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::mem;
+
+    use super::Slot;
+    use crate::{ArenaAvlTree, NodeHandle};
+
+    fn contents(tree: ArenaAvlTree<u32>) -> Vec<u32> {
+        tree.into_iter().collect()
+    }
+
+    #[test]
+    fn stale_handles_are_rejected_after_a_remove() {
+        let mut tree = ArenaAvlTree::new();
+        let handle = tree.insert(7);
+        tree.insert(8);
+        assert_eq!(tree.get(handle), Some(&7));
+        assert!(tree.remove(&7));
+
+        // 9 takes the freed slot, but not the old handle.
+        let reused = tree.insert(9);
+        assert_eq!(reused.index, handle.index);
+        assert_eq!(tree.get(handle), None);
+        assert_eq!(tree.get_mut(handle), None);
+        assert_eq!(tree.remove_handle(handle), None);
+        assert_eq!(tree.remove_handle(reused), Some(9));
+        assert_eq!(contents(tree), [8]);
+    }
+
+    #[test]
+    fn handles_stay_valid_across_shrink_to_fit() {
+        let mut tree = ArenaAvlTree::new();
+        let handles: Vec<NodeHandle> = (0..100).map(|value| tree.insert(value)).collect();
+        for value in (0..100).filter(|value| value % 3 != 0 || *value > 60) {
+            tree.remove(&value);
+        }
+        tree.shrink_to_fit();
+
+        assert_eq!(tree.capacity(), 61);
+        for (value, &handle) in handles.iter().enumerate() {
+            let live = value % 3 == 0 && value <= 60;
+            assert_eq!(tree.get(handle), live.then_some(&(value as u32)));
+        }
+
+        // The vacant slots below the last live node are reused first.
+        let handle = tree.insert(1000);
+        assert_eq!(handle.index, 1);
+        assert_eq!(tree.capacity(), 61);
+    }
+
+    #[test]
+    fn compact_reports_moved_handles() {
+        let mut tree = ArenaAvlTree::new();
+        let mut handles: Vec<NodeHandle> = (0..50).map(|value| tree.insert(value)).collect();
+        for value in (0..50).step_by(2) {
+            tree.remove(&value);
+        }
+
+        tree.compact(|old, new| {
+            let slot = handles.iter_mut().find(|handle| **handle == old).unwrap();
+            *slot = new;
+        });
+        assert_eq!(tree.capacity(), 25);
+        for (value, &handle) in handles.iter().enumerate().skip(1).step_by(2) {
+            assert_eq!(tree.get(handle), Some(&(value as u32)));
+        }
+        assert_eq!(contents(tree), (1..50).step_by(2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn inserts_after_reserve_do_not_reallocate() {
+        let mut tree = ArenaAvlTree::new();
+        tree.insert(0);
+        tree.remove(&0);
+        tree.reserve(100);
+        let capacity = tree.capacity();
+        let slots = tree.slots.as_ptr();
+        assert!(capacity >= 100);
+
+        for value in 0..100 {
+            tree.insert(value);
+        }
+        assert_eq!(tree.capacity(), capacity);
+        assert_eq!(tree.slots.as_ptr(), slots);
+
+        let tree = ArenaAvlTree::<u32>::with_capacity(10);
+        assert!(tree.capacity() >= 10);
+    }
+
+    #[test]
+    fn memory_usage_counts_live_and_spare_slots() {
+        let mut tree = ArenaAvlTree::with_capacity(16);
+        for value in 0..10u32 {
+            tree.insert(value);
+        }
+        tree.remove(&3);
+
+        let slot = mem::size_of::<Slot<u32>>();
+        let usage = tree.memory_usage();
+        assert_eq!(usage.nodes, 9);
+        assert_eq!(usage.node_bytes, 9 * slot);
+        assert_eq!(usage.spare_bytes, (tree.capacity() - 9) * slot);
+        assert_eq!(usage.value_heap_bytes, 0);
+        assert_eq!(usage.total(), tree.capacity() * slot);
+
+        let usage = tree.memory_usage_with(|value| *value as usize);
+        assert_eq!(usage.value_heap_bytes, 45 - 3);
+    }
+}
@@ -0,0 +1,218 @@
+//! Compact binary save/load format.
+//!
+//! Layout (version 1), all integers little-endian:
+//!
+//! | offset | size | content                                  |
+//! |--------|------|------------------------------------------|
+//! | 0      | 4    | magic bytes `AVLT`                       |
+//! | 4      | 1    | format version, currently `1`            |
+//! | 5      | 8    | number of values `n` as `u64`            |
+//! | 13     | ...  | `n` values in ascending order            |
+//!
+//! Each value is written with its [`BinaryCodec`] implementation. Integers
+//! use their fixed-width little-endian representation, `bool` is one byte,
+//! `char` is its `u32` scalar value and `String` is a `u64` byte length
+//! followed by the UTF-8 bytes.
+
+use std::io::{self, Read, Write};
+
+use crate::AvlTree;
+
+const MAGIC: &[u8; 4] = b"AVLT";
+const VERSION: u8 = 1;
+
+pub trait BinaryCodec: Sized {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self>;
+}
+
+macro_rules! impl_codec_for_int {
+    ($($t:ty),*) => {
+        $(
+            impl BinaryCodec for $t {
+                fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                    writer.write_all(&self.to_le_bytes())
+                }
+
+                fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+                    let mut buf = [0; core::mem::size_of::<$t>()];
+                    reader.read_exact(&mut buf)?;
+                    Ok(<$t>::from_le_bytes(buf))
+                }
+            }
+        )*
+    };
+}
+
+impl_codec_for_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl BinaryCodec for usize {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u64).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        usize::try_from(u64::decode(reader)?).map_err(|_| invalid_data("usize out of range"))
+    }
+}
+
+impl BinaryCodec for isize {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as i64).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        isize::try_from(i64::decode(reader)?).map_err(|_| invalid_data("isize out of range"))
+    }
+}
+
+impl BinaryCodec for bool {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u8).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        match u8::decode(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid_data("invalid bool")),
+        }
+    }
+}
+
+impl BinaryCodec for char {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u32).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        char::from_u32(u32::decode(reader)?).ok_or_else(|| invalid_data("invalid char"))
+    }
+}
+
+impl BinaryCodec for String {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (self.len() as u64).encode(writer)?;
+        writer.write_all(self.as_bytes())
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let len = u64::decode(reader)?;
+        let mut bytes = Vec::new();
+        reader.take(len).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        String::from_utf8(bytes).map_err(|_| invalid_data("invalid UTF-8"))
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl<V: Ord + BinaryCodec> AvlTree<V> {
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        VERSION.encode(&mut writer)?;
+        (self.len() as u64).encode(&mut writer)?;
+
+        let mut result = Ok(());
+        self.walk(&mut |value| {
+            if result.is_ok() {
+                result = value.encode(&mut writer);
+            }
+        });
+        result
+    }
+
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<AvlTree<V>> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not an AVL tree file"));
+        }
+
+        let version = u8::decode(&mut reader)?;
+        if version != VERSION {
+            return Err(invalid_data("unsupported format version"));
+        }
+
+        let len = usize::decode(&mut reader)?;
+        let mut values: Vec<V> = Vec::with_capacity(len.min(4096));
+        for _ in 0..len {
+            let value = V::decode(&mut reader)?;
+            if values.last().is_some_and(|last| *last >= value) {
+                return Err(invalid_data("values are not strictly ascending"));
+            }
+            values.push(value);
+        }
+
+        Ok(AvlTree::from_sorted(&mut values.into_iter(), len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::AvlTree;
+
+    fn round_trip<V: Ord + super::BinaryCodec>(tree: &AvlTree<V>) -> AvlTree<V> {
+        let mut bytes = Vec::new();
+        tree.write_to(&mut bytes).unwrap();
+        AvlTree::read_from(&bytes[..]).unwrap()
+    }
+
+    #[test]
+    fn values_round_trip() {
+        let mut numbers = AvlTree::new();
+        for value in [-5_i64, 0, i64::MIN, i64::MAX, 42] {
+            numbers.insert(value);
+        }
+        let decoded = round_trip(&numbers);
+        assert!(decoded.iter().eq(numbers.iter()));
+        assert_eq!(decoded.check_invariants(), Ok(()));
+
+        let mut strings = AvlTree::new();
+        for value in ["", "ünïcode", "plain"] {
+            strings.insert(String::from(value));
+        }
+        assert!(round_trip(&strings).iter().eq(strings.iter()));
+        assert!(round_trip(&AvlTree::<char>::new()).is_empty());
+    }
+
+    #[test]
+    fn header_records_the_length() {
+        let mut tree = AvlTree::new();
+        for value in 0..300_u16 {
+            tree.insert(value);
+        }
+        let mut bytes = Vec::new();
+        tree.write_to(&mut bytes).unwrap();
+
+        assert_eq!(&bytes[..5], b"AVLT\x01");
+        assert_eq!(bytes[5..13], 300_u64.to_le_bytes());
+        assert_eq!(bytes.len(), 13 + 300 * 2);
+    }
+
+    #[test]
+    fn damaged_input_is_rejected() {
+        let mut tree = AvlTree::new();
+        tree.insert(7_u32);
+        let mut bytes = Vec::new();
+        tree.write_to(&mut bytes).unwrap();
+
+        let kind = |bytes: &[u8]| AvlTree::<u32>::read_from(bytes).err().map(|err| err.kind());
+        assert_eq!(
+            kind(&bytes[..bytes.len() - 1]),
+            Some(io::ErrorKind::UnexpectedEof)
+        );
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert_eq!(kind(&bad), Some(io::ErrorKind::InvalidData));
+        let mut bad = bytes;
+        bad[4] = 2;
+        assert_eq!(kind(&bad), Some(io::ErrorKind::InvalidData));
+    }
+}
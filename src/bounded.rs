@@ -0,0 +1,164 @@
+use core::borrow::Borrow;
+
+use crate::{AvlTree, Iter};
+
+/// Which end of a full [`BoundedAvlTree`] gives way to a new value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Evict {
+    /// Keeps the largest values, as for a top-k set.
+    #[default]
+    Smallest,
+    /// Keeps the smallest values.
+    Largest,
+}
+
+/// A tree holding at most `capacity` values, which evicts from one end
+/// when an insert would exceed it.
+pub struct BoundedAvlTree<V> {
+    tree: AvlTree<V>,
+    capacity: usize,
+    evict: Evict,
+}
+
+impl<V: Ord> BoundedAvlTree<V> {
+    pub fn new(capacity: usize) -> BoundedAvlTree<V> {
+        BoundedAvlTree {
+            tree: AvlTree::new(),
+            capacity,
+            evict: Evict::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn eviction(&self) -> Evict {
+        self.evict
+    }
+
+    pub fn set_eviction(&mut self, evict: Evict) {
+        self.evict = evict;
+    }
+
+    /// Inserts `value` and returns the value evicted to make room, which is
+    /// `value` itself if it would be the one to go. Inserting a value that
+    /// is already present changes nothing.
+    pub fn insert(&mut self, value: V) -> Option<V> {
+        if self.tree.contains(&value) {
+            return None;
+        }
+        if self.tree.len() < self.capacity {
+            self.tree.insert(value);
+            return None;
+        }
+
+        let loses = match (self.evict, self.tree.min(), self.tree.max()) {
+            (Evict::Smallest, Some(min), _) => value < *min,
+            (Evict::Largest, _, Some(max)) => value > *max,
+            _ => true,
+        };
+        if loses {
+            return Some(value);
+        }
+
+        let evicted = self.pop_evicted();
+        self.tree.insert(value);
+        evicted
+    }
+
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.remove(value)
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.contains(value)
+    }
+
+    pub fn min(&self) -> Option<&V> {
+        self.tree.min()
+    }
+
+    pub fn max(&self) -> Option<&V> {
+        self.tree.max()
+    }
+
+    pub fn iter(&self) -> Iter<'_, V> {
+        self.tree.iter()
+    }
+
+    pub fn into_tree(self) -> AvlTree<V> {
+        self.tree
+    }
+
+    fn pop_evicted(&mut self) -> Option<V> {
+        let rank = match self.evict {
+            Evict::Smallest => 0,
+            Evict::Largest => self.tree.len().checked_sub(1)?,
+        };
+        self.tree.take_nth(rank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{BoundedAvlTree, Evict};
+
+    #[test]
+    fn evicts_the_smallest_by_default() {
+        let mut tree = BoundedAvlTree::new(3);
+        for value in [5, 1, 9] {
+            assert_eq!(tree.insert(value), None);
+        }
+
+        assert_eq!(tree.insert(7), Some(1));
+        assert_eq!(tree.insert(0), Some(0));
+        assert_eq!(tree.insert(9), None);
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), [5, 7, 9]);
+    }
+
+    #[test]
+    fn evicts_the_largest_when_asked() {
+        let mut tree = BoundedAvlTree::new(3);
+        tree.set_eviction(Evict::Largest);
+        for value in [4, 8, 2] {
+            tree.insert(value);
+        }
+
+        assert_eq!(tree.insert(3), Some(8));
+        assert_eq!(tree.insert(10), Some(10));
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), [2, 3, 4]);
+
+        assert!(tree.remove(&3));
+        assert_eq!(tree.insert(10), None);
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.max(), Some(&10));
+    }
+
+    #[test]
+    fn zero_capacity_keeps_nothing() {
+        let mut tree = BoundedAvlTree::new(0);
+
+        assert_eq!(tree.insert(1), Some(1));
+        assert!(tree.is_empty());
+    }
+}
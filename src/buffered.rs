@@ -0,0 +1,180 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::borrow::Borrow;
+
+use crate::AvlTree;
+
+const DEFAULT_BUFFER_LEN: usize = 1024;
+
+/// A tree that collects inserts and removes in a sorted buffer and, once
+/// the buffer is full, merges them into the tree in bulk by splitting and
+/// joining instead of descending once per value.
+///
+/// The buffer keeps only the latest operation per value, so a value
+/// inserted and removed again before a flush never reaches the tree.
+/// Removing a value that is not buffered removes it from the tree at once,
+/// as the buffer would need an owned value to stage it. Flushing emits no
+/// observer events.
+pub struct BufferedAvlTree<V> {
+    tree: AvlTree<V>,
+    buffer: BTreeMap<V, bool>,
+    buffer_len: usize,
+}
+
+impl<V: Ord> Default for BufferedAvlTree<V> {
+    fn default() -> Self {
+        BufferedAvlTree::with_buffer_len(DEFAULT_BUFFER_LEN)
+    }
+}
+
+impl<V: Ord> BufferedAvlTree<V> {
+    pub fn new() -> BufferedAvlTree<V> {
+        BufferedAvlTree::default()
+    }
+
+    pub fn with_buffer_len(buffer_len: usize) -> BufferedAvlTree<V> {
+        BufferedAvlTree {
+            tree: AvlTree::new(),
+            buffer: BTreeMap::new(),
+            buffer_len,
+        }
+    }
+
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn insert(&mut self, value: V) {
+        self.stage(value, true);
+    }
+
+    pub fn remove<Q>(&mut self, value: &Q)
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match self.buffer.get_mut(value) {
+            Some(insert) => *insert = false,
+            None => {
+                self.tree.remove(value);
+            }
+        }
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match self.buffer.get(value) {
+            Some(&insert) => insert,
+            None => self.tree.contains(value),
+        }
+    }
+
+    /// Merges the buffered operations into the tree.
+    pub fn flush(&mut self) {
+        let ops: Vec<_> = core::mem::take(&mut self.buffer).into_iter().collect();
+        self.tree.apply_sorted(ops);
+    }
+
+    /// The tree with all buffered operations applied.
+    pub fn as_tree(&mut self) -> &AvlTree<V> {
+        self.flush();
+        &self.tree
+    }
+
+    pub fn into_tree(mut self) -> AvlTree<V> {
+        self.flush();
+        self.tree
+    }
+
+    fn stage(&mut self, value: V, insert: bool) {
+        self.buffer.insert(value, insert);
+        if self.buffer.len() >= self.buffer_len {
+            self.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::vec::Vec;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::{AvlTree, BufferedAvlTree};
+
+    fn contents(tree: &AvlTree<u32>) -> Vec<u32> {
+        tree.iter().copied().collect()
+    }
+
+    #[test]
+    fn flushes_once_the_buffer_is_full() {
+        let mut buffered = BufferedAvlTree::with_buffer_len(4);
+        for value in [3, 1, 2] {
+            buffered.insert(value);
+        }
+        assert_eq!(buffered.buffered(), 3);
+        assert!(buffered.tree.is_empty());
+        assert!(buffered.contains(&2));
+
+        // The latest operation per value wins, whatever order they came in.
+        buffered.remove(&3);
+        buffered.insert(2);
+        assert_eq!(buffered.buffered(), 3);
+        buffered.insert(0);
+        assert_eq!(buffered.buffered(), 0);
+        assert_eq!(contents(&buffered.tree), [0, 1, 2]);
+    }
+
+    #[test]
+    fn remove_then_insert_in_the_buffer() {
+        let mut buffered = BufferedAvlTree::with_buffer_len(100);
+        for value in 0..10 {
+            buffered.insert(value);
+        }
+        buffered.flush();
+
+        // 5 is only in the tree, so removing it takes effect at once.
+        buffered.remove(&5);
+        assert!(!buffered.tree.contains(&5));
+        buffered.insert(5);
+        assert!(buffered.contains(&5));
+        buffered.remove(&5);
+        assert!(!buffered.contains(&5));
+        buffered.insert(5);
+        buffered.insert(20);
+        buffered.remove(&20);
+
+        assert_eq!(contents(buffered.as_tree()), (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn flush_emits_no_events() {
+        let events = Arc::new(AtomicUsize::new(0));
+        let mut buffered = BufferedAvlTree::with_buffer_len(usize::MAX);
+        let counter = Arc::clone(&events);
+        buffered
+            .tree
+            .set_observer(move |_| _ = counter.fetch_add(1, Ordering::Relaxed));
+
+        for value in 0..1000 {
+            buffered.insert(value);
+        }
+        buffered.flush();
+        // Re-inserting a present value stages it, so the remove is staged
+        // too and the batch removes it.
+        for value in (0..1000).step_by(2) {
+            buffered.insert(value);
+            buffered.remove(&value);
+        }
+        buffered.flush();
+        assert_eq!(events.load(Ordering::Relaxed), 0);
+        assert_eq!(buffered.tree.len(), 500);
+        assert_eq!(buffered.tree.check_invariants(), Ok(()));
+    }
+}
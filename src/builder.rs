@@ -0,0 +1,127 @@
+use crate::AvlTree;
+
+/// What `insert` does with a value equal to one already in the tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Keep the stored value and drop the new one.
+    #[default]
+    KeepExisting,
+    /// Drop the stored value and store the new one in its place. Useful when
+    /// `Ord` only compares part of the value.
+    Replace,
+}
+
+/// How much a tree verifies after every insert and remove.
+///
+/// Debug builds always check the nodes a mutation rebalances against their
+/// children. `Full` additionally runs [`AvlTree::check_invariants`] and
+/// panics on a violation, which costs O(n) per mutation. It is the default
+/// with the `self-check` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfCheck {
+    Off,
+    Full,
+}
+
+impl Default for SelfCheck {
+    fn default() -> Self {
+        if cfg!(feature = "self-check") {
+            SelfCheck::Full
+        } else {
+            SelfCheck::Off
+        }
+    }
+}
+
+/// Configures an [`AvlTree`] before it is created.
+///
+/// A capacity preallocates that many nodes, so the first inserts do not
+/// allocate, see [`AvlTree::reserve`]. There is no custom comparator: wrap
+/// values in a newtype with the desired `Ord` instead.
+#[derive(Debug, Clone, Default)]
+pub struct AvlTreeBuilder {
+    duplicates: DuplicatePolicy,
+    capacity: usize,
+    free_list_limit: usize,
+    self_check: SelfCheck,
+    max_imbalance: Option<u8>,
+}
+
+impl AvlTreeBuilder {
+    pub fn new() -> AvlTreeBuilder {
+        AvlTreeBuilder::default()
+    }
+
+    pub fn duplicates(mut self, policy: DuplicatePolicy) -> AvlTreeBuilder {
+        self.duplicates = policy;
+        self
+    }
+
+    pub fn capacity(mut self, capacity: usize) -> AvlTreeBuilder {
+        self.capacity = capacity;
+        self
+    }
+
+    pub fn free_list_limit(mut self, limit: usize) -> AvlTreeBuilder {
+        self.free_list_limit = limit;
+        self
+    }
+
+    pub fn self_check(mut self, level: SelfCheck) -> AvlTreeBuilder {
+        self.self_check = level;
+        self
+    }
+
+    /// Lets balance factors range up to `limit` in either direction before
+    /// a subtree is rotated, instead of 1. Trees get taller by a constant
+    /// factor in exchange for fewer rotations on write-heavy workloads. The
+    /// limit is clamped to between 1 and 8, which keeps heights in range.
+    pub fn max_imbalance(mut self, limit: u8) -> AvlTreeBuilder {
+        self.max_imbalance = Some(limit.clamp(1, 8));
+        self
+    }
+
+    pub fn build<V: Ord>(self) -> AvlTree<V> {
+        let mut tree = AvlTree::new();
+        let configured = self.duplicates != DuplicatePolicy::default()
+            || self.self_check != SelfCheck::default()
+            || self.max_imbalance.is_some_and(|limit| limit != 1)
+            || self.free_list_limit > 0;
+
+        if configured {
+            let extras = tree.extras_mut();
+            extras.duplicates = self.duplicates;
+            extras.self_check = self.self_check;
+            extras.max_imbalance = self.max_imbalance.unwrap_or(1);
+            extras.free_list_limit = self.free_list_limit;
+        }
+        tree.reserve(self.capacity);
+        tree
+    }
+}
+
+impl<V: Ord> AvlTree<V> {
+    pub fn duplicate_policy(&self) -> DuplicatePolicy {
+        self.extras
+            .as_ref()
+            .map_or_else(DuplicatePolicy::default, |extras| extras.duplicates)
+    }
+
+    pub fn self_check_level(&self) -> SelfCheck {
+        self.extras
+            .as_ref()
+            .map_or_else(SelfCheck::default, |extras| extras.self_check)
+    }
+
+    pub fn max_imbalance(&self) -> u8 {
+        self.extras
+            .as_ref()
+            .map_or(1, |extras| extras.max_imbalance)
+    }
+
+    pub(crate) fn set_max_imbalance(&mut self, limit: u8) {
+        if limit != self.max_imbalance() {
+            self.extras_mut().max_imbalance = limit;
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use core::{cmp, iter::Peekable};
+
+use crate::{AvlTree, Iter};
+
+/// An element present in only one of the two trees compared by
+/// [`AvlTree::diff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diff<T> {
+    OnlyInSelf(T),
+    OnlyInOther(T),
+}
+
+/// Iterator over the differences between two trees, see [`AvlTree::diff`].
+pub struct Diffs<'a, V> {
+    this: Peekable<Iter<'a, V>>,
+    other: Peekable<Iter<'a, V>>,
+}
+
+impl<V: Ord> AvlTree<V> {
+    /// Yields the values in only one of the two trees in ascending order, by
+    /// walking both in step.
+    pub fn diff<'a>(&'a self, other: &'a AvlTree<V>) -> Diffs<'a, V> {
+        Diffs {
+            this: self.iter().peekable(),
+            other: other.iter().peekable(),
+        }
+    }
+}
+
+impl<'a, V: Ord> Iterator for Diffs<'a, V> {
+    type Item = Diff<&'a V>;
+
+    fn next(&mut self) -> Option<Diff<&'a V>> {
+        loop {
+            let order = match (self.this.peek(), self.other.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => cmp::Ordering::Less,
+                (None, Some(_)) => cmp::Ordering::Greater,
+                (Some(this), Some(other)) => this.cmp(other),
+            };
+
+            match order {
+                cmp::Ordering::Less => return self.this.next().map(Diff::OnlyInSelf),
+                cmp::Ordering::Greater => return self.other.next().map(Diff::OnlyInOther),
+                cmp::Ordering::Equal => {
+                    self.this.next();
+                    self.other.next();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Graphviz DOT export.
+//!
+//! Every node becomes `n<i>`, numbered in pre-order, labelled with its value
+//! and height. Edges carry an `L` or `R` label because Graphviz does not
+//! guarantee that children are drawn in declaration order.
+
+use alloc::string::{String, ToString};
+use core::fmt::{self, Write};
+
+use crate::{AvlTree, Link};
+
+impl<V: Ord + fmt::Display> AvlTree<V> {
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        self.write_dot(&mut out)
+            .expect("writing to a String cannot fail");
+        out
+    }
+
+    pub fn write_dot<W: Write>(&self, out: &mut W) -> fmt::Result {
+        writeln!(out, "digraph AvlTree {{")?;
+        writeln!(out, "    node [shape=circle];")?;
+        write_node(&self.root, out, &mut 0)?;
+        writeln!(out, "}}")
+    }
+}
+
+fn write_node<V: fmt::Display, W: Write>(
+    link: &Link<V>,
+    out: &mut W,
+    next_id: &mut usize,
+) -> Result<Option<usize>, fmt::Error> {
+    let Some(node) = link else {
+        return Ok(None);
+    };
+
+    let id = *next_id;
+    *next_id += 1;
+
+    write!(out, "    n{} [label=\"", id)?;
+    write_escaped(&node.val.to_string(), out)?;
+    writeln!(out, "\\nh={}\"];", node.height)?;
+
+    for (child, side) in [(&node.left, 'L'), (&node.right, 'R')] {
+        if let Some(child_id) = write_node(child, out, next_id)? {
+            writeln!(out, "    n{} -> n{} [label=\"{}\"];", id, child_id, side)?;
+        }
+    }
+
+    Ok(Some(id))
+}
+
+fn write_escaped<W: Write>(label: &str, out: &mut W) -> fmt::Result {
+    for c in label.chars() {
+        match c {
+            '"' | '\\' => write!(out, "\\{}", c)?,
+            '\n' => out.write_str("\\n")?,
+            _ => out.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::AvlTree;
+
+    #[test]
+    fn dot_output() {
+        let mut tree = AvlTree::new();
+        for value in [2, 1, 3] {
+            tree.insert(value);
+        }
+        assert_eq!(
+            tree.to_dot(),
+            "digraph AvlTree {\n    node [shape=circle];\n    n0 [label=\"2\\nh=2\"];\n    \
+             n1 [label=\"1\\nh=1\"];\n    n0 -> n1 [label=\"L\"];\n    \
+             n2 [label=\"3\\nh=1\"];\n    n0 -> n2 [label=\"R\"];\n}\n"
+        );
+    }
+}
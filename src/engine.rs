@@ -0,0 +1,76 @@
+//! Low-level access to the tree's nodes, enabled by the `unstable`
+//! feature. Nothing here follows semver yet.
+//!
+//! [`NodeRef`] exposes the node structure read-only, so derived structures
+//! can compute their own summaries over it. The structural building blocks
+//! are [`join`] and [`AvlTree::split3`]; rotations are not exposed since a
+//! single rotation on a balanced tree can leave it unbalanced.
+
+use alloc::boxed::Box;
+
+use crate::{split, AvlTree, AvlTreeNode};
+
+/// A shared reference to a node of an [`AvlTree`].
+pub struct NodeRef<'a, V> {
+    node: &'a AvlTreeNode<V>,
+}
+
+impl<V> Clone for NodeRef<'_, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V> Copy for NodeRef<'_, V> {}
+
+impl<'a, V> NodeRef<'a, V> {
+    pub fn value(self) -> &'a V {
+        &self.node.val
+    }
+
+    /// Height of the subtree rooted here, 1 for a leaf.
+    pub fn height(self) -> usize {
+        self.node.height as usize
+    }
+
+    /// Height of the left subtree minus that of the right one.
+    pub fn balance(self) -> isize {
+        self.node.get_balance()
+    }
+
+    pub fn left(self) -> Option<NodeRef<'a, V>> {
+        self.node.left.as_deref().map(|node| NodeRef { node })
+    }
+
+    pub fn right(self) -> Option<NodeRef<'a, V>> {
+        self.node.right.as_deref().map(|node| NodeRef { node })
+    }
+}
+
+impl<V: Ord> AvlTree<V> {
+    pub fn root_node(&self) -> Option<NodeRef<'_, V>> {
+        self.root.as_deref().map(|node| NodeRef { node })
+    }
+}
+
+/// Joins `left`, `middle` and `right` into one tree. When every value in
+/// `left` is less than `middle` and every value in `right` greater, this
+/// takes time proportional to the difference of their heights; otherwise
+/// the values are inserted one by one, which is slower but still correct.
+pub fn join<V: Ord>(mut left: AvlTree<V>, middle: V, mut right: AvlTree<V>) -> AvlTree<V> {
+    let ordered =
+        left.max().is_none_or(|max| *max < middle) && right.min().is_none_or(|min| *min > middle);
+
+    if !ordered {
+        left.insert(middle);
+        for value in right {
+            left.insert(value);
+        }
+        return left;
+    }
+
+    let middle = Box::new(AvlTreeNode::new(middle));
+    left.root = Some(split::join(left.root.take(), middle, right.root.take()));
+    left.set_max_imbalance(left.max_imbalance().max(right.max_imbalance()));
+    left
+}
@@ -0,0 +1,135 @@
+use alloc::{
+    alloc::{self as global, Layout},
+    boxed::Box,
+    vec::Vec,
+};
+use core::{error::Error, fmt};
+
+use crate::{get_height, AvlTree, AvlTreeNode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocError;
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "memory allocation failed")
+    }
+}
+
+impl Error for AllocError {}
+
+impl<V: Ord> AvlTree<V> {
+    /// Like [`insert`](AvlTree::insert), but reports allocation failure
+    /// instead of aborting. The value is handed back with the error.
+    pub fn try_insert(&mut self, value: V) -> Result<bool, (V, AllocError)> {
+        let height = get_height(&self.root) as usize;
+        let mut directions = Vec::new();
+        let mut path = Vec::new();
+        if directions.try_reserve_exact(height).is_err() || path.try_reserve_exact(height).is_err()
+        {
+            return Err((value, AllocError));
+        }
+
+        self.insert_with(value, directions, path, None, AvlTree::try_alloc_node)
+    }
+
+    /// Inserts values until one fails to allocate, which is handed back
+    /// with the error. The values after it are not consumed.
+    pub fn try_extend<I: IntoIterator<Item = V>>(
+        &mut self,
+        iter: I,
+    ) -> Result<(), (V, AllocError)> {
+        for value in iter {
+            self.try_insert(value)?;
+        }
+        Ok(())
+    }
+
+    fn try_alloc_node(&mut self, value: V) -> Result<Box<AvlTreeNode<V>>, (V, AllocError)> {
+        let node = AvlTreeNode::new(value);
+
+        if let Some(slot) = self.pop_free_node() {
+            return Ok(Box::write(slot, node));
+        }
+
+        let layout = Layout::new::<AvlTreeNode<V>>();
+        // SAFETY: a node always contains its height and two links, so the
+        // layout is never zero-sized.
+        let ptr = unsafe { global::alloc(layout) }.cast::<AvlTreeNode<V>>();
+        if ptr.is_null() {
+            return Err((node.val, AllocError));
+        }
+
+        // SAFETY: `ptr` is a fresh allocation with the layout of a node,
+        // which is exactly what `Box::from_raw` expects to own.
+        unsafe {
+            ptr.write(node);
+            Ok(Box::from_raw(ptr))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::{string::String, vec::Vec};
+    use core::{
+        alloc::{GlobalAlloc, Layout},
+        cell::Cell,
+    };
+    use std::alloc::System;
+
+    use crate::{AllocError, AvlTreeBuilder, SelfCheck};
+
+    std::thread_local! {
+        static FAIL_ALLOC: Cell<bool> = const { Cell::new(false) };
+    }
+
+    /// Fails every allocation on a thread while its `FAIL_ALLOC` is set.
+    struct FailingAlloc;
+
+    unsafe impl GlobalAlloc for FailingAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            if FAIL_ALLOC.get() {
+                return core::ptr::null_mut();
+            }
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOC: FailingAlloc = FailingAlloc;
+
+    fn failing<R>(f: impl FnOnce() -> R) -> R {
+        FAIL_ALLOC.set(true);
+        let result = f();
+        FAIL_ALLOC.set(false);
+        result
+    }
+
+    #[test]
+    fn failed_inserts_hand_the_value_back() {
+        // The full self-check allocates while walking the tree.
+        let mut tree = AvlTreeBuilder::new().self_check(SelfCheck::Off).build();
+        let value = String::from("kept");
+        let (value, err) = failing(|| tree.try_insert(value)).unwrap_err();
+        assert_eq!((value.as_str(), err), ("kept", AllocError));
+        assert!(tree.is_empty());
+
+        // A reserved node, in an empty tree with no search path, needs no
+        // allocation at all.
+        tree.reserve(1);
+        assert_eq!(failing(|| tree.try_insert(value)), Ok(true));
+
+        let mut values = [String::from("a"), String::from("b")].into_iter();
+        let (value, _) = failing(|| tree.try_extend(&mut values)).unwrap_err();
+        assert_eq!(value, "a");
+        assert_eq!(values.next().as_deref(), Some("b"));
+        assert_eq!(tree.iter().collect::<Vec<_>>(), ["kept"]);
+    }
+}
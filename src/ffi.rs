@@ -0,0 +1,230 @@
+//! C interface, enabled by the `ffi` feature.
+//!
+//! Two tree types are exposed as opaque handles: [`AvlI64`] for `int64_t`
+//! keys and [`AvlBytes`] for byte-string keys, which are copied into the
+//! tree and ordered lexicographically. Each handle is created by its `_new`
+//! function and must be released with the matching `_free` function.
+//!
+//! Iteration calls a callback for every key in ascending order, passing
+//! through an opaque context pointer; returning `false` from the callback
+//! stops the iteration early.
+//!
+//! The `avl-ffi` package in `ffi/` builds these functions as a static and
+//! a shared library for linking from C.
+
+use alloc::boxed::Box;
+use core::{ffi::c_void, slice};
+
+use crate::AvlTree;
+
+pub struct AvlI64(AvlTree<i64>);
+
+pub struct AvlBytes(AvlTree<Box<[u8]>>);
+
+pub type AvlI64Visitor = extern "C" fn(key: i64, context: *mut c_void) -> bool;
+
+pub type AvlBytesVisitor = extern "C" fn(key: *const u8, len: usize, context: *mut c_void) -> bool;
+
+#[no_mangle]
+pub extern "C" fn avl_i64_new() -> *mut AvlI64 {
+    Box::into_raw(Box::new(AvlI64(AvlTree::new())))
+}
+
+/// # Safety
+///
+/// `tree` must be null or a handle from `avl_i64_new` that has not been
+/// freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn avl_i64_free(tree: *mut AvlI64) {
+    if !tree.is_null() {
+        drop(Box::from_raw(tree));
+    }
+}
+
+/// Returns whether `key` was not present before.
+///
+/// # Safety
+///
+/// `tree` must be a live handle from `avl_i64_new`.
+#[no_mangle]
+pub unsafe extern "C" fn avl_i64_insert(tree: *mut AvlI64, key: i64) -> bool {
+    (*tree).0.insert(key)
+}
+
+/// Returns whether `key` was present.
+///
+/// # Safety
+///
+/// `tree` must be a live handle from `avl_i64_new`.
+#[no_mangle]
+pub unsafe extern "C" fn avl_i64_remove(tree: *mut AvlI64, key: i64) -> bool {
+    (*tree).0.remove(&key)
+}
+
+/// # Safety
+///
+/// `tree` must be a live handle from `avl_i64_new`.
+#[no_mangle]
+pub unsafe extern "C" fn avl_i64_contains(tree: *const AvlI64, key: i64) -> bool {
+    (*tree).0.contains(&key)
+}
+
+/// # Safety
+///
+/// `tree` must be a live handle from `avl_i64_new` and must not be
+/// modified by `visit`.
+#[no_mangle]
+pub unsafe extern "C" fn avl_i64_for_each(
+    tree: *const AvlI64,
+    visit: AvlI64Visitor,
+    context: *mut c_void,
+) {
+    for &key in (*tree).0.iter() {
+        if !visit(key, context) {
+            break;
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn avl_bytes_new() -> *mut AvlBytes {
+    Box::into_raw(Box::new(AvlBytes(AvlTree::new())))
+}
+
+/// # Safety
+///
+/// `tree` must be null or a handle from `avl_bytes_new` that has not been
+/// freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn avl_bytes_free(tree: *mut AvlBytes) {
+    if !tree.is_null() {
+        drop(Box::from_raw(tree));
+    }
+}
+
+/// Copies the key into the tree. Returns whether it was not present before.
+///
+/// # Safety
+///
+/// `tree` must be a live handle from `avl_bytes_new`, and `key` must point
+/// to `len` readable bytes (it may be null if `len` is 0).
+#[no_mangle]
+pub unsafe extern "C" fn avl_bytes_insert(tree: *mut AvlBytes, key: *const u8, len: usize) -> bool {
+    (*tree).0.insert(bytes(key, len).into())
+}
+
+/// Returns whether the key was present.
+///
+/// # Safety
+///
+/// As for `avl_bytes_insert`.
+#[no_mangle]
+pub unsafe extern "C" fn avl_bytes_remove(tree: *mut AvlBytes, key: *const u8, len: usize) -> bool {
+    (*tree).0.remove(bytes(key, len))
+}
+
+/// # Safety
+///
+/// As for `avl_bytes_insert`.
+#[no_mangle]
+pub unsafe extern "C" fn avl_bytes_contains(
+    tree: *const AvlBytes,
+    key: *const u8,
+    len: usize,
+) -> bool {
+    (*tree).0.contains(bytes(key, len))
+}
+
+/// The key pointer passed to `visit` is only valid during the call.
+///
+/// # Safety
+///
+/// `tree` must be a live handle from `avl_bytes_new` and must not be
+/// modified by `visit`.
+#[no_mangle]
+pub unsafe extern "C" fn avl_bytes_for_each(
+    tree: *const AvlBytes,
+    visit: AvlBytesVisitor,
+    context: *mut c_void,
+) {
+    for key in (*tree).0.iter() {
+        if !visit(key.as_ptr(), key.len(), context) {
+            break;
+        }
+    }
+}
+
+unsafe fn bytes<'a>(key: *const u8, len: usize) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(key, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::{ffi::c_void, ptr, slice};
+
+    use super::*;
+
+    extern "C" fn collect_i64(key: i64, context: *mut c_void) -> bool {
+        let keys = unsafe { &mut *context.cast::<Vec<i64>>() };
+        keys.push(key);
+        keys.len() < 3
+    }
+
+    extern "C" fn collect_bytes(key: *const u8, len: usize, context: *mut c_void) -> bool {
+        let keys = unsafe { &mut *context.cast::<Vec<Vec<u8>>>() };
+        keys.push(unsafe { slice::from_raw_parts(key, len) }.to_vec());
+        true
+    }
+
+    #[test]
+    fn i64_handle() {
+        let tree = avl_i64_new();
+        unsafe {
+            for key in [5, -1, 3, 9] {
+                assert!(avl_i64_insert(tree, key));
+            }
+            assert!(!avl_i64_insert(tree, 3));
+            assert!(avl_i64_contains(tree, -1));
+            assert!(avl_i64_remove(tree, -1));
+            assert!(!avl_i64_remove(tree, -1));
+            assert!(!avl_i64_contains(tree, -1));
+
+            // The visitor stops after three keys.
+            avl_i64_insert(tree, 100);
+            let mut keys: Vec<i64> = Vec::new();
+            avl_i64_for_each(tree, collect_i64, ptr::from_mut(&mut keys).cast());
+            assert_eq!(keys, [3, 5, 9]);
+
+            avl_i64_free(tree);
+            avl_i64_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn bytes_handle() {
+        let tree = avl_bytes_new();
+        unsafe {
+            for key in [&b"pear"[..], b"apple", b"", b"fig"] {
+                assert!(avl_bytes_insert(tree, key.as_ptr(), key.len()));
+            }
+            assert!(!avl_bytes_insert(tree, b"fig".as_ptr(), 3));
+            assert!(!avl_bytes_insert(tree, ptr::null(), 0));
+            assert!(avl_bytes_contains(tree, b"apple".as_ptr(), 5));
+            assert!(!avl_bytes_contains(tree, b"app".as_ptr(), 3));
+            assert!(avl_bytes_remove(tree, b"pear".as_ptr(), 4));
+            assert!(!avl_bytes_remove(tree, b"pear".as_ptr(), 4));
+
+            let mut keys: Vec<Vec<u8>> = Vec::new();
+            avl_bytes_for_each(tree, collect_bytes, ptr::from_mut(&mut keys).cast());
+            assert_eq!(keys, [&b""[..], b"apple", b"fig"]);
+
+            avl_bytes_free(tree);
+            avl_bytes_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use core::{cmp, fmt};
+
+/// An `f64` ordered by [`f64::total_cmp`], so floats can be stored in a
+/// tree.
+///
+/// Every value has a place in the order, NaN included: negative NaNs sort
+/// before negative infinity and positive NaNs after positive infinity.
+/// `-0.0` sorts before `0.0` and the two are distinct. Use
+/// [`OrderedF64::new`] to keep NaN out instead.
+#[derive(Clone, Copy, Default)]
+pub struct OrderedF64(pub f64);
+
+impl OrderedF64 {
+    /// Rejects NaN.
+    pub fn new(value: f64) -> Option<OrderedF64> {
+        (!value.is_nan()).then_some(OrderedF64(value))
+    }
+
+    pub fn get(self) -> f64 {
+        self.0
+    }
+}
+
+impl From<f64> for OrderedF64 {
+    fn from(value: f64) -> Self {
+        OrderedF64(value)
+    }
+}
+
+impl PartialEq for OrderedF64 {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl Eq for OrderedF64 {}
+
+impl PartialOrd for OrderedF64 {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedF64 {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl core::hash::Hash for OrderedF64 {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl fmt::Debug for OrderedF64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for OrderedF64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
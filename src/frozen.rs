@@ -0,0 +1,150 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    borrow::Borrow,
+    ops::{Bound, RangeBounds},
+    slice,
+};
+
+use crate::AvlTree;
+
+pub struct FrozenAvlTree<V> {
+    values: Box<[V]>,
+}
+
+impl<V: Ord> AvlTree<V> {
+    pub fn freeze(self) -> FrozenAvlTree<V> {
+        FrozenAvlTree {
+            values: self.into_iter().collect(),
+        }
+    }
+
+    /// A frozen copy of the current values. The tree stays usable, so
+    /// queries can be served from the copy while the tree keeps changing.
+    pub fn snapshot(&self) -> FrozenAvlTree<V>
+    where
+        V: Clone,
+    {
+        FrozenAvlTree {
+            values: self.iter().cloned().collect(),
+        }
+    }
+
+    /// A frozen view of the current values by reference, without cloning
+    /// them. The tree cannot change while the view is alive.
+    pub fn frozen_view(&self) -> FrozenAvlTree<&V> {
+        FrozenAvlTree {
+            values: self.iter().collect(),
+        }
+    }
+}
+
+impl<V: Ord> FrozenAvlTree<V> {
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn min(&self) -> Option<&V> {
+        self.values.first()
+    }
+
+    pub fn max(&self) -> Option<&V> {
+        self.values.last()
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        slice_contains(&self.values, value)
+    }
+
+    pub fn rank<Q>(&self, value: &Q) -> usize
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        slice_rank(&self.values, value)
+    }
+
+    pub fn select(&self, rank: usize) -> Option<&V> {
+        self.values.get(rank)
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> slice::Iter<'_, V>
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        slice_range(&self.values, range).iter()
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, V> {
+        self.values.iter()
+    }
+
+    pub fn as_slice(&self) -> &[V] {
+        &self.values
+    }
+}
+
+pub(crate) fn slice_contains<V, Q>(values: &[V], value: &Q) -> bool
+where
+    V: Borrow<Q>,
+    Q: Ord + ?Sized,
+{
+    values
+        .binary_search_by(|probe| probe.borrow().cmp(value))
+        .is_ok()
+}
+
+pub(crate) fn slice_rank<V, Q>(values: &[V], value: &Q) -> usize
+where
+    V: Borrow<Q>,
+    Q: Ord + ?Sized,
+{
+    values.partition_point(|probe| probe.borrow() < value)
+}
+
+pub(crate) fn slice_range<V, Q, R>(values: &[V], range: R) -> &[V]
+where
+    V: Borrow<Q>,
+    Q: Ord + ?Sized,
+    R: RangeBounds<Q>,
+{
+    let start = match range.start_bound() {
+        Bound::Included(start) => values.partition_point(|v| v.borrow() < start),
+        Bound::Excluded(start) => values.partition_point(|v| v.borrow() <= start),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(end) => values.partition_point(|v| v.borrow() <= end),
+        Bound::Excluded(end) => values.partition_point(|v| v.borrow() < end),
+        Bound::Unbounded => values.len(),
+    };
+
+    &values[start..end.max(start)]
+}
+
+impl<'a, V: Ord> IntoIterator for &'a FrozenAvlTree<V> {
+    type Item = &'a V;
+    type IntoIter = slice::Iter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<V: Ord> IntoIterator for FrozenAvlTree<V> {
+    type Item = V;
+    type IntoIter = <Vec<V> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.into_vec().into_iter()
+    }
+}
@@ -0,0 +1,147 @@
+use core::ops::{Bound, RangeBounds, RangeInclusive};
+
+use crate::{AvlTree, Range};
+
+/// Values with a next and a previous value, so that the values missing
+/// from a tree form ranges. Implemented for the integer types and `char`.
+pub trait Discrete: Ord + Clone {
+    const MIN: Self;
+    const MAX: Self;
+
+    fn successor(&self) -> Option<Self>;
+    fn predecessor(&self) -> Option<Self>;
+}
+
+macro_rules! impl_discrete {
+    ($($ty:ty),*) => {$(
+        impl Discrete for $ty {
+            const MIN: Self = <$ty>::MIN;
+            const MAX: Self = <$ty>::MAX;
+
+            fn successor(&self) -> Option<Self> {
+                self.checked_add(1)
+            }
+
+            fn predecessor(&self) -> Option<Self> {
+                self.checked_sub(1)
+            }
+        }
+    )*};
+}
+
+impl_discrete!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+/// Skips the surrogate range, which is not made of `char`s.
+impl Discrete for char {
+    const MIN: Self = '\0';
+    const MAX: Self = char::MAX;
+
+    fn successor(&self) -> Option<Self> {
+        match *self {
+            '\u{D7FF}' => Some('\u{E000}'),
+            c => char::from_u32(c as u32 + 1),
+        }
+    }
+
+    fn predecessor(&self) -> Option<Self> {
+        match *self {
+            '\u{E000}' => Some('\u{D7FF}'),
+            c => char::from_u32((c as u32).checked_sub(1)?),
+        }
+    }
+}
+
+/// Iterator over the uncovered parts of a range, see [`AvlTree::gaps`].
+pub struct Gaps<'a, V> {
+    values: Range<'a, V>,
+    next: Option<V>,
+    end: V,
+}
+
+impl<V: Discrete> AvlTree<V> {
+    /// Yields the maximal ranges within `range` that contain no value of
+    /// the tree, in ascending order.
+    pub fn gaps<R: RangeBounds<V>>(&self, range: R) -> Gaps<'_, V> {
+        let start = match range.start_bound() {
+            Bound::Included(start) => Some(start.clone()),
+            Bound::Excluded(start) => start.successor(),
+            Bound::Unbounded => Some(V::MIN),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => Some(end.clone()),
+            Bound::Excluded(end) => end.predecessor(),
+            Bound::Unbounded => Some(V::MAX),
+        };
+
+        match (start, end) {
+            (Some(start), Some(end)) if start <= end => Gaps {
+                values: self.range(start.clone()..=end.clone()),
+                next: Some(start),
+                end,
+            },
+            _ => Gaps {
+                values: self.range(V::MAX..V::MIN),
+                next: None,
+                end: V::MIN,
+            },
+        }
+    }
+}
+
+impl<V: Discrete> Iterator for Gaps<'_, V> {
+    type Item = RangeInclusive<V>;
+
+    fn next(&mut self) -> Option<RangeInclusive<V>> {
+        loop {
+            let start = self.next.take().filter(|start| *start <= self.end)?;
+            match self.values.next() {
+                Some(value) if *value == start => self.next = value.successor(),
+                Some(value) => {
+                    self.next = value.successor();
+                    return value.predecessor().map(|end| start..=end);
+                }
+                None => return Some(start..=self.end.clone()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::Discrete;
+    use crate::AvlTree;
+
+    #[test]
+    fn gaps_cover_what_the_tree_does_not() {
+        let mut tree = AvlTree::new();
+        for value in [3u8, 4, 7, 255] {
+            tree.insert(value);
+        }
+
+        assert_eq!(tree.gaps(..).collect::<Vec<_>>(), [0..=2, 5..=6, 8..=254]);
+        assert_eq!(tree.gaps(4..8).collect::<Vec<_>>(), [5..=6]);
+        assert_eq!(tree.gaps(3..=4).next(), None);
+    }
+
+    #[test]
+    fn char_gaps_skip_the_surrogates() {
+        assert_eq!('\u{D7FF}'.successor(), Some('\u{E000}'));
+        assert_eq!('\u{E000}'.predecessor(), Some('\u{D7FF}'));
+        assert_eq!(char::MAX.successor(), None);
+        assert_eq!('\0'.predecessor(), None);
+
+        let mut tree = AvlTree::new();
+        tree.insert('\u{D7FE}');
+        tree.insert('\u{E001}');
+        assert_eq!(
+            tree.gaps('\u{D7FE}'..='\u{E001}').collect::<Vec<_>>(),
+            ['\u{D7FF}'..='\u{E000}']
+        );
+
+        tree.insert('\u{D7FF}');
+        tree.insert('\u{E000}');
+        assert_eq!(tree.gaps('\u{D7FE}'..='\u{E001}').next(), None);
+    }
+}
@@ -0,0 +1,451 @@
+use alloc::vec::Vec;
+use core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    hash::{BuildHasher, Hash},
+    iter::FusedIterator,
+    ops::{Bound, RangeBounds},
+};
+
+use crate::{AvlTree, Iter, Link};
+
+/// [`Polynomial`] hashes are polynomials in `BASE` over the in-order
+/// sequence of value hashes, taken modulo the Mersenne prime 2^61 - 1.
+const MODULUS: u64 = (1 << 61) - 1;
+const BASE: u64 = 0x0ad6_1c5f_3b0e_94a7;
+
+/// How a [`HashedAvlTree`] hashes single values and combines them into the
+/// hash of a subtree.
+///
+/// `combine` receives the hashes of the left subtree, of the node's own
+/// value and of the right subtree, with `empty()` for a missing subtree.
+/// When `empty()` is its identity and it is associative, like
+/// [`Polynomial`], the hash depends only on the values. Otherwise, as with
+/// a Merkle combination that feeds the three hashes to a cryptographic
+/// hash, it also depends on the tree's shape, which follows the order of
+/// the inserts and removes: equal sets built differently then hash
+/// differently, and [`reconcile_request`](HashedAvlTree::reconcile_request)
+/// still converges but exchanges more than the actual differences.
+pub trait SubtreeHash<V> {
+    type Hash: Copy + Eq;
+
+    fn empty(&self) -> Self::Hash;
+
+    fn leaf(&self, value: &V) -> Self::Hash;
+
+    fn combine(&self, left: Self::Hash, node: Self::Hash, right: Self::Hash) -> Self::Hash;
+}
+
+/// The default [`SubtreeHash`]: hashes each value with `S` and combines
+/// the hashes as a polynomial over the in-order sequence. This is
+/// associative, so two replicas holding the same values report the same
+/// hash however they were built. `S` must hash deterministically across
+/// replicas, so a randomly seeded hasher does not fit.
+///
+/// The combination is not collision resistant. It detects accidental
+/// divergence and corruption, but an adversary who can choose values can
+/// forge a matching hash. Tamper evidence needs a cryptographic
+/// [`SubtreeHash`] instead.
+#[derive(Debug, Clone, Default)]
+pub struct Polynomial<S>(pub S);
+
+/// A [`Polynomial`] hash together with `BASE` raised to the number of
+/// values, which is what concatenation needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PolynomialHash {
+    hash: u64,
+    power: u64,
+}
+
+impl PolynomialHash {
+    pub fn value(self) -> u64 {
+        self.hash
+    }
+}
+
+impl<V: Hash, S: BuildHasher> SubtreeHash<V> for Polynomial<S> {
+    type Hash = PolynomialHash;
+
+    fn empty(&self) -> PolynomialHash {
+        PolynomialHash { hash: 0, power: 1 }
+    }
+
+    fn leaf(&self, value: &V) -> PolynomialHash {
+        PolynomialHash {
+            hash: self.0.hash_one(value) % MODULUS,
+            power: BASE,
+        }
+    }
+
+    fn combine(
+        &self,
+        left: PolynomialHash,
+        node: PolynomialHash,
+        right: PolynomialHash,
+    ) -> PolynomialHash {
+        let then = |first: PolynomialHash, next: PolynomialHash| PolynomialHash {
+            hash: add(mul(first.hash, next.power), next.hash),
+            power: mul(first.power, next.power),
+        };
+        then(then(left, node), right)
+    }
+}
+
+fn mul(a: u64, b: u64) -> u64 {
+    ((a as u128 * b as u128) % MODULUS as u128) as u64
+}
+
+fn add(a: u64, b: u64) -> u64 {
+    (a + b) % MODULUS
+}
+
+/// A set that keeps a hash of every subtree up to date, so that the hash
+/// of the whole set, or of any range of it, is available in O(log n).
+///
+/// It is an [`AvlTree`] whose nodes carry their subtree's hash, recomputed
+/// by `H` on every node an insert or remove restructures.
+pub struct HashedAvlTree<V, H: SubtreeHash<V>> {
+    tree: AvlTree<Entry<V, H::Hash>>,
+    hasher: H,
+}
+
+/// The number of values in a range of a [`HashedAvlTree`] and their
+/// combined hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint<H> {
+    pub len: usize,
+    pub hash: H,
+}
+
+/// A value with its own hash and the hash of the subtree it tops. Orders
+/// by the value alone.
+struct Entry<V, H> {
+    val: V,
+    leaf: H,
+    subtree: H,
+}
+
+impl<V: PartialEq, H> PartialEq for Entry<V, H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.val == other.val
+    }
+}
+
+impl<V: Eq, H> Eq for Entry<V, H> {}
+
+impl<V: PartialOrd, H> PartialOrd for Entry<V, H> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.val.partial_cmp(&other.val)
+    }
+}
+
+impl<V: Ord, H> Ord for Entry<V, H> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.val.cmp(&other.val)
+    }
+}
+
+impl<V, H> Borrow<V> for Entry<V, H> {
+    fn borrow(&self) -> &V {
+        &self.val
+    }
+}
+
+/// In-order iterator over a [`HashedAvlTree`].
+pub struct HashedIter<'a, V, H> {
+    inner: Iter<'a, Entry<V, H>>,
+}
+
+impl<V: Ord, H: SubtreeHash<V>> HashedAvlTree<V, H> {
+    pub fn with_hasher(hasher: H) -> HashedAvlTree<V, H> {
+        HashedAvlTree {
+            tree: AvlTree::new(),
+            hasher,
+        }
+    }
+
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// The hash of every value in the set, `H::empty()` when it is empty.
+    pub fn root_hash(&self) -> H::Hash {
+        self.subtree_hash(&self.tree.root)
+    }
+
+    /// The number and combined hash of the values within `range`, in
+    /// O(log n). The fingerprint of the full range carries the
+    /// [`root_hash`](HashedAvlTree::root_hash).
+    pub fn fingerprint<R: RangeBounds<V>>(&self, range: R) -> Fingerprint<H::Hash> {
+        self.range_fingerprint(&self.tree.root, range.start_bound(), range.end_bound())
+    }
+
+    /// Inserts `value` and reports whether it was new. The value is hashed
+    /// before the tree is touched.
+    pub fn insert(&mut self, value: V) -> bool {
+        let leaf = self.hasher.leaf(&value);
+        let entry = Entry {
+            val: value,
+            leaf,
+            subtree: leaf,
+        };
+        let hasher = &self.hasher;
+        self.tree
+            .insert_augmented(entry, &mut |entry, left, right| {
+                entry.subtree = combine(hasher, left, entry.leaf, right)
+            })
+    }
+
+    pub fn remove(&mut self, value: &V) -> bool {
+        let hasher = &self.hasher;
+        self.tree
+            .remove_augmented(value, &mut |entry, left, right| {
+                entry.subtree = combine(hasher, left, entry.leaf, right)
+            })
+    }
+
+    pub fn contains(&self, value: &V) -> bool {
+        self.tree.contains(value)
+    }
+
+    pub fn iter(&self) -> HashedIter<'_, V, H::Hash> {
+        HashedIter {
+            inner: self.tree.iter(),
+        }
+    }
+
+    /// The value at `rank` in ascending order.
+    pub(crate) fn nth(&self, rank: usize) -> Option<&V> {
+        self.tree.nth(rank).map(|entry| &entry.val)
+    }
+
+    /// Appends the values within the bounds to `out`, in ascending order.
+    pub(crate) fn collect_range<'a>(
+        &'a self,
+        start: Bound<&V>,
+        end: Bound<&V>,
+        out: &mut Vec<&'a V>,
+    ) {
+        out.extend(
+            self.tree
+                .range::<V, _>((start, end))
+                .map(|entry| &entry.val),
+        );
+    }
+
+    fn subtree_hash(&self, link: &Link<Entry<V, H::Hash>>) -> H::Hash {
+        link.as_ref()
+            .map_or_else(|| self.hasher.empty(), |node| node.val.subtree)
+    }
+
+    fn range_fingerprint(
+        &self,
+        link: &Link<Entry<V, H::Hash>>,
+        start: Bound<&V>,
+        end: Bound<&V>,
+    ) -> Fingerprint<H::Hash> {
+        let Some(node) = link else {
+            return Fingerprint {
+                len: 0,
+                hash: self.hasher.empty(),
+            };
+        };
+        if (start, end) == (Bound::Unbounded, Bound::Unbounded) {
+            return Fingerprint {
+                len: node.size,
+                hash: node.val.subtree,
+            };
+        }
+        if !above(&node.val.val, start) {
+            self.range_fingerprint(&node.right, start, end)
+        } else if !below(&node.val.val, end) {
+            self.range_fingerprint(&node.left, start, end)
+        } else {
+            let left = self.range_fingerprint(&node.left, start, Bound::Unbounded);
+            let right = self.range_fingerprint(&node.right, Bound::Unbounded, end);
+            Fingerprint {
+                len: left.len + 1 + right.len,
+                hash: self.hasher.combine(left.hash, node.val.leaf, right.hash),
+            }
+        }
+    }
+}
+
+fn combine<V, H: SubtreeHash<V>>(
+    hasher: &H,
+    left: Option<&Entry<V, H::Hash>>,
+    leaf: H::Hash,
+    right: Option<&Entry<V, H::Hash>>,
+) -> H::Hash {
+    let subtree = |entry: Option<&Entry<V, H::Hash>>| {
+        entry.map_or_else(|| hasher.empty(), |entry| entry.subtree)
+    };
+    hasher.combine(subtree(left), leaf, subtree(right))
+}
+
+impl<V: Ord, H: SubtreeHash<V> + Default> Default for HashedAvlTree<V, H> {
+    fn default() -> Self {
+        HashedAvlTree::with_hasher(H::default())
+    }
+}
+
+impl<V: Ord, H: SubtreeHash<V>> Extend<V> for HashedAvlTree<V, H> {
+    fn extend<I: IntoIterator<Item = V>>(&mut self, values: I) {
+        for value in values {
+            self.insert(value);
+        }
+    }
+}
+
+impl<'a, V: Ord, H: SubtreeHash<V>> IntoIterator for &'a HashedAvlTree<V, H> {
+    type Item = &'a V;
+    type IntoIter = HashedIter<'a, V, H::Hash>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, V, H> Iterator for HashedIter<'a, V, H> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        self.inner.next().map(|entry| &entry.val)
+    }
+}
+
+impl<V, H> FusedIterator for HashedIter<'_, V, H> {}
+
+fn above<V: Ord>(value: &V, start: Bound<&V>) -> bool {
+    match start {
+        Bound::Included(start) => value >= start,
+        Bound::Excluded(start) => value > start,
+        Bound::Unbounded => true,
+    }
+}
+
+fn below<V: Ord>(value: &V, end: Bound<&V>) -> bool {
+    match end {
+        Bound::Included(end) => value <= end,
+        Bound::Excluded(end) => value < end,
+        Bound::Unbounded => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::vec::Vec;
+    use core::hash::{BuildHasher, BuildHasherDefault, Hash};
+    use std::collections::hash_map::DefaultHasher;
+
+    use super::Entry;
+    use crate::{Fingerprint, HashedAvlTree, Link, Polynomial, SubtreeHash};
+
+    type Tree = HashedAvlTree<u32, Polynomial<BuildHasherDefault<DefaultHasher>>>;
+
+    /// Recomputes every subtree hash from scratch and compares it with the
+    /// stored one.
+    fn check<V, H: SubtreeHash<V>>(hasher: &H, link: &Link<Entry<V, H::Hash>>) -> H::Hash {
+        let Some(node) = link else {
+            return hasher.empty();
+        };
+        let (left, right) = (check(hasher, &node.left), check(hasher, &node.right));
+        let expected = hasher.combine(left, node.val.leaf, right);
+        assert!(expected == node.val.subtree);
+        expected
+    }
+
+    #[test]
+    fn root_hash_ignores_insertion_order_and_history() {
+        let mut ascending = Tree::default();
+        ascending.extend(0..200);
+        let mut shuffled = Tree::default();
+        shuffled.extend((0..400).map(|i| (i * 7919) % 400));
+        for value in 200..400 {
+            assert!(shuffled.remove(&value));
+        }
+        check(ascending.hasher(), &ascending.tree.root);
+        check(shuffled.hasher(), &shuffled.tree.root);
+        assert_eq!(shuffled.tree.check_invariants(), Ok(()));
+
+        assert_eq!(ascending.len(), 200);
+        assert_eq!(ascending.root_hash(), shuffled.root_hash());
+        assert!(ascending.iter().eq(shuffled.iter()));
+
+        assert!(!shuffled.insert(5));
+        assert!(shuffled.remove(&5));
+        assert_ne!(ascending.root_hash(), shuffled.root_hash());
+        shuffled.insert(5);
+        assert_eq!(ascending.root_hash(), shuffled.root_hash());
+    }
+
+    #[test]
+    fn fingerprints_match_a_rebuilt_range() {
+        let mut tree = Tree::default();
+        tree.extend((0..100).map(|i| i * 3));
+        for (start, end) in [(0, 300), (10, 11), (13, 200), (299, 400), (50, 50)] {
+            let mut rebuilt = Tree::default();
+            rebuilt.extend(tree.iter().copied().filter(|v| (start..end).contains(v)));
+            let expected = Fingerprint {
+                len: rebuilt.len(),
+                hash: rebuilt.root_hash(),
+            };
+            assert_eq!(tree.fingerprint(start..end), expected);
+        }
+        assert_eq!(tree.fingerprint(..).hash, tree.root_hash());
+        assert_eq!(tree.fingerprint(..=3).len, 2);
+        assert_eq!(Tree::default().root_hash().value(), 0);
+        assert!(tree.iter().copied().collect::<Vec<_>>().is_sorted());
+    }
+
+    /// Hashes a node's value together with its children's hashes, so the
+    /// root hash commits to the tree's shape as well as its values.
+    #[derive(Default)]
+    struct Merkle(BuildHasherDefault<DefaultHasher>);
+
+    impl<V: Hash> SubtreeHash<V> for Merkle {
+        type Hash = u64;
+
+        fn empty(&self) -> u64 {
+            0
+        }
+
+        fn leaf(&self, value: &V) -> u64 {
+            self.0.hash_one(value)
+        }
+
+        fn combine(&self, left: u64, node: u64, right: u64) -> u64 {
+            self.0.hash_one((left, node, right))
+        }
+    }
+
+    #[test]
+    fn pluggable_hash_is_kept_up_to_date() {
+        let mut a = HashedAvlTree::<u32, Merkle>::default();
+        let mut b = HashedAvlTree::<u32, Merkle>::default();
+        for value in (0..500).map(|i| (i * 37) % 500) {
+            a.insert(value);
+            b.insert(value);
+        }
+        for value in (0..500).step_by(3) {
+            a.remove(&value);
+            b.remove(&value);
+        }
+        check(a.hasher(), &a.tree.root);
+        assert_eq!(a.root_hash(), b.root_hash());
+
+        a.remove(&1);
+        check(a.hasher(), &a.tree.root);
+        assert_ne!(a.root_hash(), b.root_hash());
+    }
+}
@@ -0,0 +1,377 @@
+use alloc::vec::Vec;
+use core::{
+    cmp,
+    error::Error,
+    fmt,
+    ptr::{self, NonNull},
+};
+
+use crate::{get_height, get_size, AvlTree, AvlTreeNode, Link, Side};
+
+/// The first violation found by [`AvlTree::check_invariants`]. Every
+/// variant carries the branches taken from the root to the offending node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantError {
+    Unordered {
+        path: Vec<Side>,
+    },
+    HeightMismatch {
+        path: Vec<Side>,
+        stored: usize,
+        computed: usize,
+    },
+    Unbalanced {
+        path: Vec<Side>,
+        balance: isize,
+    },
+    SizeMismatch {
+        path: Vec<Side>,
+        stored: usize,
+        computed: usize,
+    },
+}
+
+impl InvariantError {
+    pub fn path(&self) -> &[Side] {
+        match self {
+            InvariantError::Unordered { path }
+            | InvariantError::HeightMismatch { path, .. }
+            | InvariantError::Unbalanced { path, .. }
+            | InvariantError::SizeMismatch { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for InvariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantError::Unordered { .. } => write!(f, "node violates the search order")?,
+            InvariantError::HeightMismatch {
+                stored, computed, ..
+            } => write!(
+                f,
+                "node stores height {} but its subtrees give {}",
+                stored, computed
+            )?,
+            InvariantError::Unbalanced { balance, .. } => {
+                write!(f, "node has balance factor {}", balance)?
+            }
+            InvariantError::SizeMismatch {
+                stored, computed, ..
+            } => write!(
+                f,
+                "node stores size {} but its subtrees hold {}",
+                stored, computed
+            )?,
+        }
+
+        write!(f, " at {}", RootPath(self.path()))
+    }
+}
+
+impl Error for InvariantError {}
+
+/// Formats a value in self-check failures, see [`AvlTree::debug_values`].
+pub(crate) type DebugValue<V> = fn(&V, &mut fmt::Formatter<'_>) -> fmt::Result;
+
+/// Shows the branches from the root to a node as `root/L/R`.
+pub(crate) struct RootPath<'a>(pub(crate) &'a [Side]);
+
+impl fmt::Display for RootPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "root")?;
+        for side in self.0 {
+            match side {
+                Side::Left => write!(f, "/L")?,
+                Side::Right => write!(f, "/R")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Shows ` holding <value>` when both a value and a way to format it are
+/// known, and nothing otherwise.
+pub(crate) struct Holding<'a, V> {
+    value: Option<&'a V>,
+    debug: Option<DebugValue<V>>,
+}
+
+impl<V> fmt::Display for Holding<'_, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.value, self.debug) {
+            (Some(value), Some(debug)) => {
+                write!(f, " holding ")?;
+                debug(value, f)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<V: Ord> AvlTree<V> {
+    /// Verifies that values are in strictly ascending order, that every
+    /// stored height and size matches its subtrees and that every balance
+    /// factor is within the tree's limit, normally between -1 and 1.
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        let limit = self.max_imbalance() as isize;
+        check(&self.root, None, None, limit, &mut Vec::new()).map(|_| ())
+    }
+
+    /// Like [`check_invariants`](AvlTree::check_invariants), but first
+    /// reports any node that an earlier insert or remove could not
+    /// rebalance. Debug builds panic on that at once, release builds only
+    /// remember it, so this is the checked way to detect corruption there.
+    pub fn try_validate(&self) -> Result<(), InvariantError> {
+        match self
+            .extras
+            .as_ref()
+            .and_then(|extras| extras.corruption.as_ref())
+        {
+            Some(err) => Err(err.clone()),
+            None => self.check_invariants(),
+        }
+    }
+
+    /// Makes self-check failures show the offending node's value, not just
+    /// its path from the root.
+    pub fn debug_values(&mut self)
+    where
+        V: fmt::Debug,
+    {
+        self.extras_mut().debug_value = Some(<V as fmt::Debug>::fmt);
+    }
+
+    pub(crate) fn run_self_check(&self, operation: &str) {
+        if let Err(err) = self.check_invariants() {
+            panic!(
+                "AVL invariant broken after {}: {}{}",
+                operation,
+                err,
+                self.holding(self.value_at(err.path()))
+            );
+        }
+    }
+
+    pub(crate) fn holding<'a>(&self, value: Option<&'a V>) -> Holding<'a, V> {
+        Holding {
+            value,
+            debug: self.extras.as_ref().and_then(|extras| extras.debug_value),
+        }
+    }
+
+    fn value_at(&self, path: &[Side]) -> Option<&V> {
+        let mut node = self.root.as_deref()?;
+        for side in path {
+            node = match side {
+                Side::Left => node.left.as_deref()?,
+                Side::Right => node.right.as_deref()?,
+            };
+        }
+        Some(&node.val)
+    }
+}
+
+impl<V: Ord> AvlTree<V> {
+    /// Checks the nodes an insert or remove restructured against their
+    /// direct children. This calls `Ord`, so it only runs once the tree is
+    /// whole again and the pending events are flushed: a panicking
+    /// comparison then unwinds through an intact tree.
+    pub(crate) fn check_touched(&self, touched: &[NonNull<AvlTreeNode<V>>], operation: &str) {
+        for node in touched {
+            // SAFETY: the nodes are boxed and still in the tree, which has
+            // not been mutated since they were collected.
+            let node = unsafe { node.as_ref() };
+            if !node.is_locally_shaped(self.max_imbalance()) || !node.is_locally_ordered() {
+                let mut path = Vec::new();
+                find(&self.root, node, &mut path);
+                panic!(
+                    "AVL invariant broken at {} after {}{}",
+                    RootPath(&path),
+                    operation,
+                    self.holding(Some(&node.val))
+                );
+            }
+        }
+    }
+}
+
+impl<V> AvlTreeNode<V> {
+    /// Checks the stored height, size and balance against the direct
+    /// children only, without comparing any values.
+    pub(crate) fn is_locally_shaped(&self, max_imbalance: u8) -> bool {
+        let (left, right) = (get_height(&self.left), get_height(&self.right));
+
+        self.height == cmp::max(left, right) + 1
+            && self.size == get_size(&self.left) + get_size(&self.right) + 1
+            && (left as isize - right as isize).abs() <= max_imbalance as isize
+    }
+}
+
+impl<V: Ord> AvlTreeNode<V> {
+    pub(crate) fn is_locally_ordered(&self) -> bool {
+        self.left.as_ref().is_none_or(|left| left.val < self.val)
+            && self.right.as_ref().is_none_or(|right| right.val > self.val)
+    }
+}
+
+/// Records in `path` the branches from `link` down to `target`, found by
+/// address so that no values are compared.
+fn find<V>(link: &Link<V>, target: &AvlTreeNode<V>, path: &mut Vec<Side>) -> bool {
+    let Some(node) = link else {
+        return false;
+    };
+    if ptr::eq(&**node, target) {
+        return true;
+    }
+    for (side, child) in [(Side::Left, &node.left), (Side::Right, &node.right)] {
+        path.push(side);
+        if find(child, target, path) {
+            return true;
+        }
+        path.pop();
+    }
+    false
+}
+
+fn check<V: Ord>(
+    link: &Link<V>,
+    lower: Option<&V>,
+    upper: Option<&V>,
+    max_imbalance: isize,
+    path: &mut Vec<Side>,
+) -> Result<(usize, usize), InvariantError> {
+    let Some(node) = link else {
+        return Ok((0, 0));
+    };
+
+    if lower.is_some_and(|lower| *lower >= node.val)
+        || upper.is_some_and(|upper| *upper <= node.val)
+    {
+        return Err(InvariantError::Unordered { path: path.clone() });
+    }
+
+    path.push(Side::Left);
+    let (left, left_size) = check(&node.left, lower, Some(&node.val), max_imbalance, path)?;
+    path.pop();
+    path.push(Side::Right);
+    let (right, right_size) = check(&node.right, Some(&node.val), upper, max_imbalance, path)?;
+    path.pop();
+
+    let computed = cmp::max(left, right) + 1;
+    if node.height as usize != computed {
+        return Err(InvariantError::HeightMismatch {
+            path: path.clone(),
+            stored: node.height as usize,
+            computed,
+        });
+    }
+
+    let balance = left as isize - right as isize;
+    if balance.abs() > max_imbalance {
+        return Err(InvariantError::Unbalanced {
+            path: path.clone(),
+            balance,
+        });
+    }
+
+    let size = left_size + right_size + 1;
+    if node.size != size {
+        return Err(InvariantError::SizeMismatch {
+            path: path.clone(),
+            stored: node.size,
+            computed: size,
+        });
+    }
+
+    Ok((computed, size))
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::string::String;
+    use std::panic::{self, AssertUnwindSafe};
+
+    use crate::{AvlTree, AvlTreeBuilder, SelfCheck};
+
+    fn panic_message<R>(f: impl FnOnce() -> R) -> String {
+        let payload = panic::catch_unwind(AssertUnwindSafe(f)).err().unwrap();
+        *payload.downcast::<String>().unwrap()
+    }
+
+    /// 1..=7 as a perfect tree, with 5 at root/R/L overwritten by 50.
+    fn corrupted(check: SelfCheck) -> AvlTree<u32> {
+        let mut tree = AvlTreeBuilder::new().self_check(check).build();
+        for value in [4, 2, 6, 1, 3, 5, 7] {
+            tree.insert(value);
+        }
+        let right = tree.root.as_mut().unwrap().right.as_mut().unwrap();
+        right.left.as_mut().unwrap().val = 50;
+        tree
+    }
+
+    #[test]
+    fn full_check_reports_the_path_and_value() {
+        let mut tree = corrupted(SelfCheck::Full);
+        let message = panic_message(|| tree.run_self_check("test"));
+        assert!(
+            message.ends_with("violates the search order at root/R/L"),
+            "{message}"
+        );
+
+        tree.debug_values();
+        let message = panic_message(|| tree.run_self_check("test"));
+        assert!(message.ends_with("at root/R/L holding 50"), "{message}");
+    }
+
+    /// 1 and 2 as root and right child, with 2 claiming height 5.
+    fn overgrown() -> AvlTree<u32> {
+        let mut tree = AvlTreeBuilder::new().self_check(SelfCheck::Off).build();
+        tree.insert(1);
+        tree.insert(2);
+        tree.root.as_mut().unwrap().right.as_mut().unwrap().height = 5;
+        tree
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))]
+    fn release_builds_remember_failed_rebalancing() {
+        let mut tree = overgrown();
+        assert_eq!(tree.try_validate(), tree.check_invariants());
+
+        // The stale height makes the root rotate, and the rotation leaves 2
+        // on top with a balance factor of 2.
+        tree.insert(0);
+        let err = tree.try_validate().unwrap_err();
+        assert!(
+            matches!(err, crate::InvariantError::Unbalanced { .. }),
+            "{err}"
+        );
+        assert_eq!(err.path(), []);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn debug_builds_panic_on_failed_rebalancing() {
+        let mut tree = overgrown();
+        let message = panic_message(|| tree.insert(0));
+        assert!(
+            message.starts_with("AVL invariant broken at root"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn unwind_check_reports_the_path_and_value() {
+        let mut tree = corrupted(SelfCheck::Off);
+        tree.debug_values();
+        let message = panic_message(|| tree.insert(8));
+        assert_eq!(
+            message,
+            "AVL invariant broken at root/R after insert holding 6"
+        );
+    }
+}
@@ -0,0 +1,468 @@
+use alloc::vec::Vec;
+use core::{
+    borrow::Borrow,
+    iter::{FusedIterator, Take},
+    ops::{Bound, ControlFlow, RangeBounds},
+    ptr,
+};
+
+use crate::{AvlTree, AvlTreeNode, Link};
+
+/// In-order iterator over references, see [`AvlTree::iter`].
+pub struct Iter<'a, V> {
+    stack: Vec<&'a AvlTreeNode<V>>,
+}
+
+/// Iterator from the largest value down, see [`AvlTree::largest`].
+pub struct Descending<'a, V> {
+    stack: Vec<&'a AvlTreeNode<V>>,
+}
+
+/// In-order iterator over values and their ranks, see
+/// [`AvlTree::iter_ranked`] and [`AvlTree::range_ranked`].
+pub struct Ranked<'a, V> {
+    range: Range<'a, V>,
+    rank: usize,
+}
+
+/// In-order iterator over the values within a range, see
+/// [`AvlTree::range`].
+pub struct Range<'a, V> {
+    iter: Iter<'a, V>,
+    last: Option<&'a V>,
+}
+
+impl<V: Ord> AvlTree<V> {
+    pub fn iter(&self) -> Iter<'_, V> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left_spine(&self.root);
+        iter
+    }
+
+    /// The `k` smallest values in ascending order, visiting O(k + log n)
+    /// nodes.
+    pub fn smallest(&self, k: usize) -> Take<Iter<'_, V>> {
+        self.iter().take(k)
+    }
+
+    /// The `k` largest values in descending order, visiting O(k + log n)
+    /// nodes.
+    pub fn largest(&self, k: usize) -> Take<Descending<'_, V>> {
+        let mut iter = Descending { stack: Vec::new() };
+        iter.push_right_spine(&self.root);
+        iter.take(k)
+    }
+
+    /// The `k` values closest to `x`, nearest first, where `distance`
+    /// measures how far a value is from `x`. Walks outwards from `x` in
+    /// both directions, visiting O(log n + k) nodes; on a tie the smaller
+    /// value comes first.
+    pub fn nearest_k<D, F>(&self, x: &V, k: usize, mut distance: F) -> Vec<&V>
+    where
+        D: Ord,
+        F: FnMut(&V) -> D,
+    {
+        let mut above = self.iter();
+        above.seek(x);
+        let mut below = Descending { stack: Vec::new() };
+        let mut cur = &self.root;
+        while let Some(ref node) = cur {
+            if node.val < *x {
+                below.stack.push(node);
+                cur = &node.right;
+            } else {
+                cur = &node.left;
+            }
+        }
+
+        let mut above = above.peekable();
+        let mut below = below.peekable();
+        let mut nearest = Vec::with_capacity(k.min(self.len()));
+        while nearest.len() < k {
+            let take_below = match (below.peek(), above.peek()) {
+                (None, None) => break,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (Some(low), Some(high)) => distance(low) <= distance(high),
+            };
+            nearest.extend(if take_below {
+                below.next()
+            } else {
+                above.next()
+            });
+        }
+
+        nearest
+    }
+
+    /// Pairs every value with its rank, the number of smaller values.
+    pub fn iter_ranked(&self) -> Ranked<'_, V> {
+        self.range_ranked::<V, _>(..)
+    }
+
+    /// Pairs the values within `range` with their ranks in the whole tree.
+    /// The first rank is counted from the subtree sizes, so this takes
+    /// O(log n) plus the number of values yielded.
+    pub fn range_ranked<Q, R>(&self, range: R) -> Ranked<'_, V>
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        Ranked {
+            rank: self.start_rank(range.start_bound()),
+            range: self.range(range),
+        }
+    }
+
+    /// Inverted or empty ranges yield nothing instead of panicking.
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, V>
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let mut stack = Vec::new();
+        let mut cur = &self.root;
+        while let Some(ref node) = cur {
+            let in_range = match range.start_bound() {
+                Bound::Included(start) => node.val.borrow() >= start,
+                Bound::Excluded(start) => node.val.borrow() > start,
+                Bound::Unbounded => true,
+            };
+            if in_range {
+                stack.push(&**node);
+                cur = &node.left;
+            } else {
+                cur = &node.right;
+            }
+        }
+
+        let mut last = None;
+        let mut cur = &self.root;
+        while let Some(ref node) = cur {
+            let in_range = match range.end_bound() {
+                Bound::Included(end) => node.val.borrow() <= end,
+                Bound::Excluded(end) => node.val.borrow() < end,
+                Bound::Unbounded => true,
+            };
+            if in_range {
+                last = Some(&node.val);
+                cur = &node.right;
+            } else {
+                cur = &node.left;
+            }
+        }
+
+        Range {
+            iter: Iter { stack },
+            last,
+        }
+    }
+}
+
+impl<V: Ord> AvlTree<V> {
+    /// Whether any value lies within `range`, found by a single descent to
+    /// the first value past the start bound.
+    pub fn contains_any<Q, R>(&self, range: R) -> bool
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let mut first = None;
+        let mut cur = &self.root;
+        while let Some(ref node) = cur {
+            let in_range = match range.start_bound() {
+                Bound::Included(start) => node.val.borrow() >= start,
+                Bound::Excluded(start) => node.val.borrow() > start,
+                Bound::Unbounded => true,
+            };
+            if in_range {
+                first = Some(node.val.borrow());
+                cur = &node.left;
+            } else {
+                cur = &node.right;
+            }
+        }
+
+        first.is_some_and(|first| match range.end_bound() {
+            Bound::Included(end) => first <= end,
+            Bound::Excluded(end) => first < end,
+            Bound::Unbounded => true,
+        })
+    }
+}
+
+impl<V: Ord> AvlTree<V> {
+    /// Folds the values within `range` in ascending order, stopping at the
+    /// first `Break`. Recurses over the nodes directly instead of keeping an
+    /// iterator stack, so nothing is allocated.
+    pub fn try_fold_range<Q, R, B, C, F>(&self, range: R, init: C, mut f: F) -> ControlFlow<B, C>
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+        F: FnMut(C, &V) -> ControlFlow<B, C>,
+    {
+        try_fold_link(&self.root, &range, init, &mut f)
+    }
+}
+
+fn try_fold_link<V, Q, R, B, C, F>(
+    link: &Link<V>,
+    range: &R,
+    init: C,
+    f: &mut F,
+) -> ControlFlow<B, C>
+where
+    V: Borrow<Q>,
+    Q: Ord + ?Sized,
+    R: RangeBounds<Q>,
+    F: FnMut(C, &V) -> ControlFlow<B, C>,
+{
+    let Some(node) = link else {
+        return ControlFlow::Continue(init);
+    };
+
+    let after_start = match range.start_bound() {
+        Bound::Included(start) => node.val.borrow() >= start,
+        Bound::Excluded(start) => node.val.borrow() > start,
+        Bound::Unbounded => true,
+    };
+    let before_end = match range.end_bound() {
+        Bound::Included(end) => node.val.borrow() <= end,
+        Bound::Excluded(end) => node.val.borrow() < end,
+        Bound::Unbounded => true,
+    };
+
+    let mut acc = init;
+    if after_start {
+        acc = try_fold_link(&node.left, range, acc, f)?;
+    }
+    if after_start && before_end {
+        acc = f(acc, &node.val)?;
+    }
+    if before_end {
+        acc = try_fold_link(&node.right, range, acc, f)?;
+    }
+    ControlFlow::Continue(acc)
+}
+
+impl<'a, V> Iter<'a, V> {
+    pub(crate) fn from_node(node: &'a AvlTreeNode<V>) -> Iter<'a, V> {
+        let mut iter = Iter {
+            stack: alloc::vec![node],
+        };
+        iter.push_left_spine(&node.left);
+        iter
+    }
+
+    fn push_left_spine(&mut self, mut link: &'a Link<V>) {
+        while let Some(ref node) = link {
+            self.stack.push(node);
+            link = &node.left;
+        }
+    }
+
+    /// Skips forward so that the next value is the first one not less than
+    /// `target`, without visiting the skipped subtrees.
+    pub(crate) fn seek<Q>(&mut self, target: &Q)
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        while let Some(&node) = self.stack.last() {
+            if node.val.borrow() >= target {
+                return;
+            }
+
+            self.stack.pop();
+            let mut cur = &node.right;
+            while let Some(ref node) = cur {
+                if node.val.borrow() < target {
+                    cur = &node.right;
+                } else {
+                    self.stack.push(node);
+                    cur = &node.left;
+                }
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<&'a V> {
+        self.stack.last().map(|node| &node.val)
+    }
+}
+
+impl<V: Ord> AvlTree<V> {
+    /// Whether every probe is in the tree. Probes in ascending order are
+    /// matched in a single in-order pass that skips the subtrees between
+    /// them; a probe smaller than its predecessor restarts from the root.
+    pub fn contains_all<'q, Q, I>(&self, probes: I) -> bool
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized + 'q,
+        I: IntoIterator<Item = &'q Q>,
+    {
+        let mut cursor = self.iter();
+        let mut previous: Option<&Q> = None;
+
+        for probe in probes {
+            if previous.is_some_and(|previous| probe < previous) {
+                cursor = self.iter();
+            }
+            cursor.seek(probe);
+            if cursor.peek().is_none_or(|value| value.borrow() != probe) {
+                return false;
+            }
+            previous = Some(probe);
+        }
+
+        true
+    }
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        let node = self.stack.pop()?;
+        self.push_left_spine(&node.right);
+        Some(&node.val)
+    }
+}
+
+impl<V> FusedIterator for Iter<'_, V> {}
+
+impl<'a, V> Descending<'a, V> {
+    fn push_right_spine(&mut self, mut link: &'a Link<V>) {
+        while let Some(ref node) = link {
+            self.stack.push(node);
+            link = &node.right;
+        }
+    }
+}
+
+impl<'a, V> Iterator for Descending<'a, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        let node = self.stack.pop()?;
+        self.push_right_spine(&node.left);
+        Some(&node.val)
+    }
+}
+
+impl<V> FusedIterator for Descending<'_, V> {}
+
+impl<'a, V: Ord> Iterator for Ranked<'a, V> {
+    type Item = (usize, &'a V);
+
+    fn next(&mut self) -> Option<(usize, &'a V)> {
+        let value = self.range.next()?;
+        self.rank += 1;
+        Some((self.rank - 1, value))
+    }
+}
+
+impl<V: Ord> FusedIterator for Ranked<'_, V> {}
+
+impl<'a, V: Ord> Iterator for Range<'a, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        let last = self.last?;
+        let value = self.iter.next().filter(|value| *value <= last);
+        if value.is_none_or(|value| ptr::eq(value, last)) {
+            self.last = None;
+        }
+        value
+    }
+}
+
+impl<V: Ord> FusedIterator for Range<'_, V> {}
+
+impl<'a, V: Ord> IntoIterator for &'a AvlTree<V> {
+    type Item = &'a V;
+    type IntoIter = Iter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::ops::ControlFlow;
+
+    use crate::AvlTree;
+
+    fn tree_of(values: impl IntoIterator<Item = i32>) -> AvlTree<i32> {
+        let mut tree = AvlTree::new();
+        for value in values {
+            tree.insert(value);
+        }
+        tree
+    }
+
+    #[test]
+    fn range_ranked_yields_ranks_in_the_whole_tree() {
+        let tree = tree_of((0..100).map(|v| v * 2));
+
+        let ranked: Vec<_> = tree.range_ranked(31..=40).collect();
+        assert_eq!(
+            ranked,
+            [(16, &32), (17, &34), (18, &36), (19, &38), (20, &40)]
+        );
+        assert_eq!(tree.range_ranked(200..).next(), None);
+        assert!(tree
+            .iter_ranked()
+            .all(|(rank, value)| tree.nth(rank) == Some(value)));
+    }
+
+    #[test]
+    fn nearest_k_accepts_an_unbounded_k() {
+        let tree = tree_of([1, 4, 6, 10]);
+
+        let nearest = tree.nearest_k(&5, usize::MAX, |v| (v - 5).abs());
+        assert_eq!(
+            nearest.into_iter().copied().collect::<Vec<_>>(),
+            [4, 6, 1, 10]
+        );
+    }
+
+    #[test]
+    fn try_fold_range_stops_at_the_first_break() {
+        let tree = tree_of(0..50);
+        let mut visited = Vec::new();
+
+        let found = tree.try_fold_range(10..40, 0, |sum, &value| {
+            visited.push(value);
+            if sum + value > 30 {
+                ControlFlow::Break(value)
+            } else {
+                ControlFlow::Continue(sum + value)
+            }
+        });
+        assert_eq!(found, ControlFlow::Break(12));
+        assert_eq!(visited, [10, 11, 12]);
+
+        let sum = tree.try_fold_range(45.., 0, |sum, &value| {
+            ControlFlow::<(), _>::Continue(sum + value)
+        });
+        assert_eq!(sum, ControlFlow::Continue(45 + 46 + 47 + 48 + 49));
+    }
+
+    #[test]
+    fn contains_all_accepts_probes_in_any_order() {
+        let tree = tree_of((0..100).map(|v| v * 3));
+
+        assert!(tree.contains_all(&[3, 30, 297]));
+        assert!(tree.contains_all(&[297, 3, 30, 30]));
+        assert!(!tree.contains_all(&[3, 31, 297]));
+        assert!(!tree.contains_all(&[297, 300]));
+        assert!(tree.contains_all::<i32, _>(&[]));
+    }
+}
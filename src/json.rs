@@ -0,0 +1,494 @@
+//! JSON export and import of the exact tree shape.
+//!
+//! Every node is written as `{"value":..,"height":..,"left":..,"right":..}`,
+//! with `null` standing in for an empty subtree, so `null` is also the
+//! encoding of an empty tree. A tree built with a relaxed balance limit
+//! also gets a `"max_imbalance"` field on its root node, which the import
+//! validates the balance factors against and gives the rebuilt tree.
+
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+};
+use core::{error::Error, fmt};
+
+use crate::{AvlTree, AvlTreeNode, Height, Link};
+
+// No tree this deep can fit in memory, even with the loosest imbalance
+// limit of 8 (which allows 226 levels for 2^64 nodes), so anything deeper
+// is rejected before the recursive parser can exhaust the stack.
+const MAX_DEPTH: usize = 230;
+
+const MAX_IMBALANCE_LIMIT: usize = 8;
+
+pub trait JsonValue: Sized {
+    fn write_json(&self, out: &mut String);
+    fn read_json(text: &str) -> Option<Self>;
+}
+
+macro_rules! impl_json_for_scalar {
+    ($($t:ty),*) => {
+        $(
+            impl JsonValue for $t {
+                fn write_json(&self, out: &mut String) {
+                    out.push_str(&self.to_string());
+                }
+
+                fn read_json(text: &str) -> Option<Self> {
+                    text.parse().ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_json_for_scalar!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, bool);
+
+impl JsonValue for String {
+    fn write_json(&self, out: &mut String) {
+        out.push('"');
+        for c in self.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+    }
+
+    fn read_json(text: &str) -> Option<Self> {
+        let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+        let mut out = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            match chars.next()? {
+                '"' => out.push('"'),
+                '\\' => out.push('\\'),
+                '/' => out.push('/'),
+                'b' => out.push('\u{8}'),
+                'f' => out.push('\u{c}'),
+                'n' => out.push('\n'),
+                'r' => out.push('\r'),
+                't' => out.push('\t'),
+                'u' => {
+                    let high = read_hex4(&mut chars)?;
+                    let code = match high {
+                        0xd800..=0xdbff => {
+                            if chars.next()? != '\\' || chars.next()? != 'u' {
+                                return None;
+                            }
+                            let low = read_hex4(&mut chars)?;
+                            if !(0xdc00..=0xdfff).contains(&low) {
+                                return None;
+                            }
+                            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+                        }
+                        code => code,
+                    };
+                    out.push(char::from_u32(code)?);
+                }
+                _ => return None,
+            }
+        }
+
+        Some(out)
+    }
+}
+
+fn read_hex4(chars: &mut core::str::Chars<'_>) -> Option<u32> {
+    let mut code = 0;
+    for _ in 0..4 {
+        code = code * 16 + chars.next()?.to_digit(16)?;
+    }
+    Some(code)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonError {
+    Syntax { offset: usize },
+    InvalidValue { offset: usize },
+    MissingField { offset: usize, field: &'static str },
+    TooDeep { offset: usize },
+    HeightMismatch { offset: usize },
+    Unbalanced { offset: usize },
+    Unordered { offset: usize },
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::Syntax { offset } => write!(f, "JSON syntax error at byte {}", offset),
+            JsonError::InvalidValue { offset } => {
+                write!(f, "value at byte {} cannot be decoded", offset)
+            }
+            JsonError::MissingField { offset, field } => {
+                write!(f, "node at byte {} has no \"{}\" field", offset, field)
+            }
+            JsonError::TooDeep { offset } => {
+                write!(f, "node at byte {} is nested too deeply", offset)
+            }
+            JsonError::HeightMismatch { offset } => {
+                write!(f, "node at byte {} has a wrong height", offset)
+            }
+            JsonError::Unbalanced { offset } => {
+                write!(f, "node at byte {} violates the AVL balance", offset)
+            }
+            JsonError::Unordered { offset } => {
+                write!(f, "node at byte {} violates the search order", offset)
+            }
+        }
+    }
+}
+
+impl Error for JsonError {}
+
+impl<V: Ord + JsonValue> AvlTree<V> {
+    pub fn to_json_structure(&self) -> String {
+        let mut out = String::new();
+        let limit = self.max_imbalance();
+        write_json_structure(&self.root, (limit != 1).then_some(limit), &mut out);
+        out
+    }
+
+    pub fn from_json_structure(json: &str) -> Result<AvlTree<V>, JsonError> {
+        let mut parser = Parser {
+            text: json,
+            pos: 0,
+            max_imbalance: None,
+            first_exceeding: [None; MAX_IMBALANCE_LIMIT + 1],
+        };
+        let root = parser.parse_tree(0)?;
+        parser.skip_whitespace();
+        if parser.pos != json.len() {
+            return Err(JsonError::Syntax { offset: parser.pos });
+        }
+
+        let limit = parser.max_imbalance.unwrap_or(1);
+        if let Some(offset) = parser.first_exceeding[limit as usize] {
+            return Err(JsonError::Unbalanced { offset });
+        }
+
+        let mut tree = AvlTree::from_link(root);
+        tree.set_max_imbalance(limit);
+        Ok(tree)
+    }
+}
+
+fn write_json_structure<V: JsonValue>(link: &Link<V>, max_imbalance: Option<u8>, out: &mut String) {
+    match link {
+        None => out.push_str("null"),
+        Some(ref node) => {
+            out.push('{');
+            if let Some(limit) = max_imbalance {
+                out.push_str(&format!("\"max_imbalance\":{},", limit));
+            }
+            out.push_str("\"value\":");
+            node.val.write_json(out);
+            out.push_str(&format!(",\"height\":{},\"left\":", node.height));
+            write_json_structure(&node.left, None, out);
+            out.push_str(",\"right\":");
+            write_json_structure(&node.right, None, out);
+            out.push('}');
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    max_imbalance: Option<u8>,
+    // The root's limit may come after its subtrees, so the balance check is
+    // settled at the end: entry `l` holds the first node whose balance
+    // factor exceeds `l`.
+    first_exceeding: [Option<usize>; MAX_IMBALANCE_LIMIT + 1],
+}
+
+impl<'a> Parser<'a> {
+    fn parse_tree<V: Ord + JsonValue>(&mut self, depth: usize) -> Result<Link<V>, JsonError> {
+        self.skip_whitespace();
+        let offset = self.pos;
+
+        if self.eat_literal("null") {
+            return Ok(None);
+        }
+        if depth >= MAX_DEPTH {
+            return Err(JsonError::TooDeep { offset });
+        }
+        self.expect(b'{')?;
+
+        let mut val = None;
+        let mut height = None;
+        let mut left = None;
+        let mut right = None;
+
+        self.skip_whitespace();
+        if !self.eat(b'}') {
+            loop {
+                self.skip_whitespace();
+                let key = self.parse_key()?;
+                self.skip_whitespace();
+                self.expect(b':')?;
+                self.skip_whitespace();
+
+                match key {
+                    "value" => {
+                        let start = self.pos;
+                        let raw = self.skip_value()?;
+                        val = Some(
+                            V::read_json(raw).ok_or(JsonError::InvalidValue { offset: start })?,
+                        );
+                    }
+                    "height" => {
+                        let start = self.pos;
+                        let raw = self.skip_value()?;
+                        height = Some(
+                            raw.parse::<Height>()
+                                .map_err(|_| JsonError::InvalidValue { offset: start })?,
+                        );
+                    }
+                    "max_imbalance" if depth == 0 => {
+                        let start = self.pos;
+                        let raw = self.skip_value()?;
+                        self.max_imbalance = Some(
+                            raw.parse::<u8>()
+                                .ok()
+                                .filter(|limit| (1..=MAX_IMBALANCE_LIMIT as u8).contains(limit))
+                                .ok_or(JsonError::InvalidValue { offset: start })?,
+                        );
+                    }
+                    "left" => left = self.parse_tree(depth + 1)?,
+                    "right" => right = self.parse_tree(depth + 1)?,
+                    _ => {
+                        self.skip_value()?;
+                    }
+                }
+
+                self.skip_whitespace();
+                if self.eat(b'}') {
+                    break;
+                }
+                self.expect(b',')?;
+            }
+        }
+
+        let val = val.ok_or(JsonError::MissingField {
+            offset,
+            field: "value",
+        })?;
+        let height = height.ok_or(JsonError::MissingField {
+            offset,
+            field: "height",
+        })?;
+
+        if left.as_ref().is_some_and(|node| *node.max() >= val)
+            || right.as_ref().is_some_and(|node| *node.min() <= val)
+        {
+            return Err(JsonError::Unordered { offset });
+        }
+
+        let mut node = AvlTreeNode {
+            val,
+            height: 0,
+            size: 0,
+            left,
+            right,
+        };
+        node.update_height();
+
+        if node.height != height {
+            return Err(JsonError::HeightMismatch { offset });
+        }
+        let balance = node.get_balance().unsigned_abs();
+        if balance > MAX_IMBALANCE_LIMIT {
+            return Err(JsonError::Unbalanced { offset });
+        }
+        for first in &mut self.first_exceeding[..balance] {
+            first.get_or_insert(offset);
+        }
+
+        Ok(Some(Box::new(node)))
+    }
+
+    fn parse_key(&mut self) -> Result<&'a str, JsonError> {
+        let start = self.pos;
+        self.expect(b'"')?;
+        while let Some(&b) = self.text.as_bytes().get(self.pos) {
+            match b {
+                b'"' => {
+                    self.pos += 1;
+                    return Ok(&self.text[start + 1..self.pos - 1]);
+                }
+                b'\\' => return Err(JsonError::Syntax { offset: self.pos }),
+                _ => self.pos += 1,
+            }
+        }
+        Err(JsonError::Syntax { offset: self.pos })
+    }
+
+    fn skip_value(&mut self) -> Result<&'a str, JsonError> {
+        let bytes = self.text.as_bytes();
+        let start = self.pos;
+        let mut nesting = 0_usize;
+        let mut in_string = false;
+
+        while let Some(&b) = bytes.get(self.pos) {
+            if in_string {
+                match b {
+                    b'\\' => self.pos += 1,
+                    b'"' => in_string = false,
+                    _ => (),
+                }
+            } else {
+                match b {
+                    b'"' => in_string = true,
+                    b'{' | b'[' => nesting += 1,
+                    b'}' | b']' if nesting == 0 => break,
+                    b'}' | b']' => nesting -= 1,
+                    b',' if nesting == 0 => break,
+                    _ => (),
+                }
+            }
+            self.pos += 1;
+        }
+
+        if in_string || nesting != 0 {
+            return Err(JsonError::Syntax { offset: self.pos });
+        }
+
+        let raw = self.text[start..self.pos].trim_end();
+        if raw.is_empty() {
+            return Err(JsonError::Syntax { offset: start });
+        }
+        Ok(raw)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .text
+            .as_bytes()
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.text.as_bytes().get(self.pos) == Some(&byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_literal(&mut self, literal: &str) -> bool {
+        if self.text[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), JsonError> {
+        if self.eat(byte) {
+            Ok(())
+        } else {
+            Err(JsonError::Syntax { offset: self.pos })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, string::String};
+
+    use crate::{AvlTree, AvlTreeBuilder, JsonError};
+
+    fn relaxed(limit: u8, len: u32) -> AvlTree<u32> {
+        let mut tree = AvlTreeBuilder::new().max_imbalance(limit).build();
+        for value in 0..len {
+            tree.insert(value);
+        }
+        tree
+    }
+
+    #[test]
+    fn shape_and_values_round_trip() {
+        let mut tree = AvlTree::new();
+        for value in [5_i32, -3, 8, 1, 0, 9, 7] {
+            tree.insert(value);
+        }
+        let decoded = AvlTree::<i32>::from_json_structure(&tree.to_json_structure()).unwrap();
+        assert!(decoded.same_shape(&tree));
+        assert!(decoded.iter().eq(tree.iter()));
+        assert_eq!(decoded.max_imbalance(), 1);
+
+        let mut strings = AvlTree::new();
+        for value in ["quote \"", "back\\slash", "tab\t", "ünï"] {
+            strings.insert(String::from(value));
+        }
+        let json = strings.to_json_structure();
+        let decoded = AvlTree::<String>::from_json_structure(&json).unwrap();
+        assert!(decoded.iter().eq(strings.iter()));
+
+        let empty = AvlTree::<u8>::new().to_json_structure();
+        assert!(AvlTree::<u8>::from_json_structure(&empty)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn relaxed_trees_round_trip() {
+        for limit in 2..=8 {
+            let tree = relaxed(limit, 500);
+            let json = tree.to_json_structure();
+
+            let decoded = AvlTree::<u32>::from_json_structure(&json).unwrap();
+            assert_eq!(decoded.max_imbalance(), limit);
+            assert!(decoded.same_shape(&tree));
+            assert_eq!(decoded.check_invariants(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn limit_is_checked_wherever_it_appears() {
+        // 1 -> 2 -> 3 leans right by two levels.
+        let chain = r#""value":1,"height":3,"left":null,"right":{"value":2,"height":2,"left":null,"right":{"value":3,"height":1,"left":null,"right":null}}"#;
+
+        let strict = format!("{{{}}}", chain);
+        assert_eq!(
+            AvlTree::<u32>::from_json_structure(&strict).err(),
+            Some(JsonError::Unbalanced { offset: 0 })
+        );
+
+        let leading = format!("{{\"max_imbalance\":2,{}}}", chain);
+        let trailing = format!("{{{},\"max_imbalance\":2}}", chain);
+        for json in [leading, trailing] {
+            let tree = AvlTree::<u32>::from_json_structure(&json).unwrap();
+            assert_eq!(tree.max_imbalance(), 2);
+            assert_eq!(tree.len(), 3);
+        }
+    }
+
+    #[test]
+    fn out_of_range_limit_is_rejected() {
+        let json = r#"{"max_imbalance":9,"value":1,"height":1,"left":null,"right":null}"#;
+        assert_eq!(
+            AvlTree::<u32>::from_json_structure(json).err(),
+            Some(JsonError::InvalidValue { offset: 17 })
+        );
+    }
+}
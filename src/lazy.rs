@@ -0,0 +1,269 @@
+use alloc::vec::Vec;
+use core::{borrow::Borrow, cmp, iter::FusedIterator, ops::RangeBounds};
+
+use crate::{AvlTree, Iter, Range};
+
+const DEFAULT_COMPACTION_RATIO: f64 = 0.5;
+
+pub struct LazyAvlTree<V> {
+    tree: AvlTree<Entry<V>>,
+    len: usize,
+    tombstones: usize,
+    compaction_ratio: f64,
+}
+
+struct Entry<V> {
+    val: V,
+    deleted: bool,
+}
+
+impl<V: Ord> PartialEq for Entry<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.val == other.val
+    }
+}
+
+impl<V: Ord> Eq for Entry<V> {}
+
+impl<V: Ord> PartialOrd for Entry<V> {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<V: Ord> Ord for Entry<V> {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.val.cmp(&other.val)
+    }
+}
+
+impl<V> Borrow<V> for Entry<V> {
+    fn borrow(&self) -> &V {
+        &self.val
+    }
+}
+
+/// In-order iterator over the live values of a [`LazyAvlTree`].
+pub struct LazyIter<'a, V> {
+    inner: Iter<'a, Entry<V>>,
+}
+
+/// Iterator over the live values of a [`LazyAvlTree`] within a range.
+pub struct LazyRange<'a, V> {
+    inner: Range<'a, Entry<V>>,
+}
+
+impl<V> Default for LazyAvlTree<V> {
+    fn default() -> Self {
+        LazyAvlTree {
+            tree: AvlTree::default(),
+            len: 0,
+            tombstones: 0,
+            compaction_ratio: DEFAULT_COMPACTION_RATIO,
+        }
+    }
+}
+
+impl<V: Ord> LazyAvlTree<V> {
+    pub fn new() -> LazyAvlTree<V> {
+        LazyAvlTree::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn tombstones(&self) -> usize {
+        self.tombstones
+    }
+
+    pub fn compaction_ratio(&self) -> f64 {
+        self.compaction_ratio
+    }
+
+    pub fn set_compaction_ratio(&mut self, ratio: f64) {
+        self.compaction_ratio = ratio;
+    }
+
+    pub fn insert(&mut self, value: V) {
+        match self.tree.get_mut_unordered(&value) {
+            Some(entry) if entry.deleted => {
+                entry.val = value;
+                entry.deleted = false;
+                self.tombstones -= 1;
+            }
+            Some(_) => return,
+            None => {
+                self.tree.insert(Entry {
+                    val: value,
+                    deleted: false,
+                });
+            }
+        }
+        self.len += 1;
+    }
+
+    pub fn remove(&mut self, value: &V) -> bool {
+        match self.tree.get_mut_unordered(value) {
+            Some(entry) if !entry.deleted => {
+                entry.deleted = true;
+                self.len -= 1;
+                self.tombstones += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn contains(&self, value: &V) -> bool {
+        self.tree.get(value).is_some_and(|entry| !entry.deleted)
+    }
+
+    /// The live values in ascending order, skipping tombstones.
+    pub fn iter(&self) -> LazyIter<'_, V> {
+        LazyIter {
+            inner: self.tree.iter(),
+        }
+    }
+
+    /// The live values within `range` in ascending order, skipping
+    /// tombstones.
+    pub fn range<R: RangeBounds<V>>(&self, range: R) -> LazyRange<'_, V> {
+        LazyRange {
+            inner: self.tree.range::<V, _>(range),
+        }
+    }
+
+    pub fn compact(&mut self) -> bool {
+        let total = self.len + self.tombstones;
+        if self.tombstones == 0 || (self.tombstones as f64) <= self.compaction_ratio * total as f64
+        {
+            return false;
+        }
+
+        self.compact_now();
+        true
+    }
+
+    pub fn compact_now(&mut self) {
+        let live: Vec<Entry<V>> = core::mem::take(&mut self.tree)
+            .into_iter()
+            .filter(|entry| !entry.deleted)
+            .collect();
+        let len = live.len();
+
+        self.tree = AvlTree::from_sorted(&mut live.into_iter(), len);
+        self.tombstones = 0;
+    }
+}
+
+impl<V: Ord> IntoIterator for LazyAvlTree<V> {
+    type Item = V;
+    type IntoIter = <Vec<V> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tree
+            .into_iter()
+            .filter(|entry| !entry.deleted)
+            .map(|entry| entry.val)
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
+
+impl<'a, V: Ord> IntoIterator for &'a LazyAvlTree<V> {
+    type Item = &'a V;
+    type IntoIter = LazyIter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, V> Iterator for LazyIter<'a, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        self.inner
+            .find(|entry| !entry.deleted)
+            .map(|entry| &entry.val)
+    }
+}
+
+impl<V> FusedIterator for LazyIter<'_, V> {}
+
+impl<'a, V: Ord> Iterator for LazyRange<'a, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        self.inner
+            .find(|entry| !entry.deleted)
+            .map(|entry| &entry.val)
+    }
+}
+
+impl<V: Ord> FusedIterator for LazyRange<'_, V> {}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::LazyAvlTree;
+
+    fn tree_of(values: impl IntoIterator<Item = u32>) -> LazyAvlTree<u32> {
+        let mut tree = LazyAvlTree::new();
+        for value in values {
+            tree.insert(value);
+        }
+        tree
+    }
+
+    #[test]
+    fn iteration_skips_tombstones() {
+        let mut tree = tree_of(0..20);
+        for value in (0..20).filter(|v| v % 3 == 0) {
+            assert!(tree.remove(&value));
+        }
+        assert!(!tree.remove(&3));
+        tree.insert(6);
+
+        let live: Vec<u32> = (0..20).filter(|v| v % 3 != 0 || *v == 6).collect();
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), live);
+        assert_eq!(tree.len(), live.len());
+        assert_eq!(
+            tree.range(5..=12).copied().collect::<Vec<_>>(),
+            [5, 6, 7, 8, 10, 11]
+        );
+        assert_eq!(tree.range(..1).count(), 0);
+        assert_eq!(tree.range(18..).copied().collect::<Vec<_>>(), [19]);
+        assert_eq!((&tree).into_iter().count(), live.len());
+    }
+
+    #[test]
+    fn compacts_once_tombstones_exceed_the_ratio() {
+        let mut tree = tree_of(0..10);
+        tree.set_compaction_ratio(0.3);
+
+        // 3 of 10 is exactly the ratio, which does not compact yet.
+        for value in 0..3 {
+            tree.remove(&value);
+        }
+        assert!(!tree.compact());
+        assert_eq!(tree.tombstones(), 3);
+
+        tree.remove(&3);
+        assert!(tree.compact());
+        assert_eq!(tree.tombstones(), 0);
+        assert!(!tree.compact());
+        assert_eq!(tree.tree.len(), 6);
+        assert_eq!(
+            tree.iter().copied().collect::<Vec<_>>(),
+            (4..10).collect::<Vec<_>>()
+        );
+        assert!(!tree.contains(&3));
+    }
+}
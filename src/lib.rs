@@ -1,15 +1,157 @@
-use std::{
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{boxed::Box, collections::BTreeSet, vec::Vec};
+use core::{
+    borrow::Borrow,
     cmp,
+    convert::Infallible,
     fmt::{self, Formatter},
+    mem::MaybeUninit,
+    ptr::{self, NonNull},
 };
 
+use invariants::DebugValue;
+use metrics::Counters;
+use observer::{Observer, Pending};
+use recorder::Recorder;
+
+mod arena;
+#[cfg(feature = "std")]
+mod binary;
+mod bounded;
+mod buffered;
+mod builder;
+mod diff;
+mod dot;
+#[cfg(feature = "unstable")]
+pub mod engine;
+mod fallible;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod float;
+mod frozen;
+mod gaps;
+mod hashed;
+mod invariants;
+mod iter;
+mod json;
+mod lazy;
+mod mapped;
+mod memory;
+mod merge;
+mod mermaid;
+mod metrics;
+mod modify;
+mod observer;
+mod rank;
+mod reconcile;
+mod recorder;
+mod render;
+#[cfg(feature = "std")]
+mod sharded;
+mod small;
+mod split;
+mod structure;
+#[cfg(feature = "svg")]
+mod svg;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod transaction;
+#[cfg(feature = "std")]
+mod wal;
+
+pub use arena::{ArenaAvlTree, NodeHandle};
+#[cfg(feature = "std")]
+pub use binary::BinaryCodec;
+pub use bounded::{BoundedAvlTree, Evict};
+pub use buffered::BufferedAvlTree;
+pub use builder::{AvlTreeBuilder, DuplicatePolicy, SelfCheck};
+pub use diff::{Diff, Diffs};
+pub use fallible::AllocError;
+pub use float::OrderedF64;
+pub use frozen::FrozenAvlTree;
+pub use gaps::{Discrete, Gaps};
+pub use hashed::{Fingerprint, HashedAvlTree, HashedIter, Polynomial, PolynomialHash, SubtreeHash};
+pub use invariants::InvariantError;
+pub use iter::{Descending, Iter, Range, Ranked};
+pub use json::{JsonError, JsonValue};
+pub use lazy::{LazyAvlTree, LazyIter, LazyRange};
+pub use mapped::{MappedAvlTree, MappedLayoutError, Pod};
+pub use memory::MemoryUsage;
+#[cfg(feature = "metrics")]
+pub use metrics::Metrics;
+pub use observer::Event;
+pub use rank::RangeStep;
+pub use reconcile::{ReconcileMessage, ReconcileRange};
+pub use recorder::{Frame, RecordedNode};
+pub use render::{DisplayOptions, Layout, TreeDisplay};
+#[cfg(feature = "std")]
+pub use sharded::{ShardedAvlTree, ShardedIter};
+pub use small::{SmallAvlTree, SmallIter};
+pub use structure::{NodeInfo, Side, SubtreeView};
+pub use transaction::Transaction;
+#[cfg(feature = "std")]
+pub use wal::LoggedAvlTree;
+
 pub struct AvlTree<V> {
-    root: Option<Box<AvlTreeNode<V>>>,
+    root: Link<V>,
+    counters: Counters,
+    extras: Option<Box<Extras<V>>>,
+}
+
+/// Settings and hooks that most trees never use, kept behind one pointer
+/// so that a tree without them is no larger than its root link. `None`
+/// stands for the defaults.
+struct Extras<V> {
+    free_nodes: Vec<Box<MaybeUninit<AvlTreeNode<V>>>>,
+    free_list_limit: usize,
+    observer: Option<Observer<V>>,
+    recorder: Option<Recorder<V>>,
+    pending: Vec<Pending<V>>,
+    duplicates: DuplicatePolicy,
+    self_check: SelfCheck,
+    max_imbalance: u8,
+    debug_value: Option<DebugValue<V>>,
+    corruption: Option<InvariantError>,
+}
+
+impl<V> Default for Extras<V> {
+    fn default() -> Self {
+        Extras {
+            free_nodes: Vec::new(),
+            free_list_limit: 0,
+            observer: None,
+            recorder: None,
+            pending: Vec::new(),
+            duplicates: DuplicatePolicy::default(),
+            self_check: SelfCheck::default(),
+            max_imbalance: 1,
+            debug_value: None,
+            corruption: None,
+        }
+    }
 }
 
 impl<V> Default for AvlTree<V> {
     fn default() -> Self {
-        AvlTree { root: None }
+        AvlTree {
+            root: None,
+            counters: Counters::default(),
+            extras: None,
+        }
+    }
+}
+
+impl<V> Drop for AvlTree<V> {
+    fn drop(&mut self) {
+        let mut stack: Vec<Box<AvlTreeNode<V>>> = self.root.take().into_iter().collect();
+
+        while let Some(mut node) = stack.pop() {
+            stack.extend(node.left.take());
+            stack.extend(node.right.take());
+        }
     }
 }
 
@@ -18,6 +160,39 @@ impl<V: Ord> AvlTree<V> {
         AvlTree::default()
     }
 
+    /// A tree that can take `capacity` values before allocating, see
+    /// [`AvlTree::reserve`].
+    pub fn with_capacity(capacity: usize) -> AvlTree<V> {
+        let mut tree = AvlTree::new();
+        tree.reserve(capacity);
+        tree
+    }
+
+    /// Allocates nodes up front so that the next `additional` inserts need
+    /// no allocation. The free list may exceed its limit to hold them, but
+    /// removes only recycle nodes while it is below the limit, so it shrinks
+    /// back once the reserve is used up.
+    pub fn reserve(&mut self, additional: usize) {
+        if additional == 0 {
+            return;
+        }
+
+        let extras = self.extras_mut();
+        let missing = additional.saturating_sub(extras.free_nodes.len());
+        extras.free_nodes.reserve_exact(missing);
+        extras
+            .free_nodes
+            .extend((0..missing).map(|_| Box::new_uninit()));
+    }
+
+    pub fn len(&self) -> usize {
+        get_size(&self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
     pub fn min(&self) -> Option<&V> {
         self.root.as_ref().map(|node| node.min())
     }
@@ -26,286 +201,508 @@ impl<V: Ord> AvlTree<V> {
         self.root.as_ref().map(|node| node.max())
     }
 
-    pub fn remove(&mut self, value: &V) -> bool {
-        match self.root {
-            None => {
-                return false;
-            }
-            Some(ref mut node) => match value.cmp(&node.val) {
+    /// The smallest and the largest value, which are the same for a single
+    /// element.
+    pub fn bounds(&self) -> Option<(&V, &V)> {
+        self.root.as_ref().map(|node| (node.min(), node.max()))
+    }
+
+    pub fn free_list_limit(&self) -> usize {
+        self.extras
+            .as_ref()
+            .map_or(0, |extras| extras.free_list_limit)
+    }
+
+    /// Sets how many removed nodes are kept for reuse, dropping any spare
+    /// nodes beyond it, including reserved ones.
+    pub fn set_free_list_limit(&mut self, limit: usize) {
+        if limit == self.free_list_limit() && self.spare_nodes() <= limit {
+            return;
+        }
+
+        let extras = self.extras_mut();
+        extras.free_list_limit = limit;
+        extras.free_nodes.truncate(limit);
+    }
+
+    /// Drops every spare node, reserved or recycled, and the free list's
+    /// own buffer. The free list limit is kept.
+    pub fn shrink_to_fit(&mut self) {
+        if let Some(extras) = self.extras.as_mut() {
+            extras.free_nodes = Vec::new();
+        }
+    }
+
+    fn spare_nodes(&self) -> usize {
+        self.extras
+            .as_ref()
+            .map_or(0, |extras| extras.free_nodes.len())
+    }
+
+    pub fn rebuild_balanced(&mut self) {
+        let values: Vec<V> = AvlTree::from_link(self.root.take()).into_iter().collect();
+        let len = values.len();
+        self.root = AvlTreeNode::from_sorted(&mut values.into_iter(), len);
+    }
+
+    /// Inserts `value` and reports whether it was new. An equal value
+    /// already in the tree is kept or replaced as the
+    /// [`DuplicatePolicy`] says, and either way `false` is returned.
+    pub fn insert(&mut self, value: V) -> bool {
+        let Ok(inserted) = self.insert_with(value, Vec::new(), Vec::new(), None, |tree, value| {
+            Ok::<_, Infallible>(tree.alloc_node(value))
+        });
+        inserted
+    }
+
+    /// Like [`insert`](AvlTree::insert), but afterwards calls `augment` on
+    /// every node the insert restructured, children before their parents,
+    /// with the values of the node and of its two children. This keeps a
+    /// summary of each subtree stored in its top value up to date, provided
+    /// every mutation of the tree goes through these augmented methods.
+    ///
+    /// `augment` runs once the tree is whole again. If it panics, the tree
+    /// stays intact but some summaries are stale.
+    pub(crate) fn insert_augmented(&mut self, value: V, augment: Augment<'_, V>) -> bool {
+        let Ok(inserted) = self.insert_with(
+            value,
+            Vec::new(),
+            Vec::new(),
+            Some(augment),
+            |tree, value| Ok::<_, Infallible>(tree.alloc_node(value)),
+        );
+        inserted
+    }
+
+    /// Searches without taking the tree apart, so a panicking `Ord` leaves
+    /// it as it was.
+    fn insert_with<E, F>(
+        &mut self,
+        value: V,
+        mut directions: Vec<Direction>,
+        path: Path<V>,
+        augment: Option<Augment<'_, V>>,
+        alloc: F,
+    ) -> Result<bool, E>
+    where
+        F: FnOnce(&mut Self, V) -> Result<Box<AvlTreeNode<V>>, E>,
+    {
+        let mut cur = &self.root;
+
+        while let Some(ref node) = cur {
+            self.counters.record_comparison();
+            match value.cmp(&node.val) {
                 cmp::Ordering::Less => {
-                    node.left.remove(value);
+                    directions.push(Direction::Left);
+                    cur = &node.left;
                 }
                 cmp::Ordering::Greater => {
-                    node.right.remove(value);
+                    directions.push(Direction::Right);
+                    cur = &node.right;
                 }
-                cmp::Ordering::Equal => match (node.left.root.take(), node.right.root.take()) {
-                    (None, None) => {
-                        self.root.take();
-                        return true;
-                    }
-                    (Some(rnode), None) => {
-                        self.root.replace(rnode);
-                        return true;
-                    }
-                    (None, Some(rnode)) => {
-                        self.root.replace(rnode);
-                        return true;
+                cmp::Ordering::Equal => {
+                    if self.duplicate_policy() == DuplicatePolicy::Replace {
+                        if let Some(ref mut node) = self.link_at(&directions) {
+                            node.val = value;
+                        }
                     }
-                    (Some(lnode), Some(rnode)) => {
-                        let mut right_tree = AvlTree { root: Some(rnode) };
-                        let mut new_node = right_tree.take_min_node();
+                    return Ok(false);
+                }
+            }
+        }
 
-                        new_node.left = AvlTree { root: Some(lnode) };
-                        new_node.right = right_tree;
+        let mut node = alloc(self, value)?;
+        let (path, _) = self.detach(&directions, path);
+        self.defer_inserted(&node);
+        let mut touched = Touched::new(augment.is_some());
+        touched.push(&mut node);
+        self.root = self.unwind(path, Some(node), true, &mut touched);
+        self.flush_events();
+        self.finish_touched(&touched, augment, "insert");
+        self.record_snapshot();
 
-                        self.root.replace(new_node);
-                    }
-                },
-            },
+        if self.self_check_level() == SelfCheck::Full {
+            self.run_self_check("insert");
         }
 
-        self.root
-            .as_mut()
-            .expect("remove: self is empty")
-            .update_height();
+        Ok(true)
+    }
 
-        self.remove_balance();
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.take_by(None, |val| Some(value.cmp(val.borrow())))
+            .is_some()
+    }
+
+    /// Like [`remove`](AvlTree::remove), but calls `augment` as
+    /// [`insert_augmented`](AvlTree::insert_augmented) does.
+    pub(crate) fn remove_augmented<Q>(&mut self, value: &Q, augment: Augment<'_, V>) -> bool
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.take_by(Some(augment), |val| Some(value.cmp(val.borrow())))
+            .is_some()
+    }
 
-        true
+    /// Removes the value if `pred` approves of the stored one, in a single
+    /// traversal.
+    pub fn remove_if<Q, F>(&mut self, value: &Q, pred: F) -> Option<V>
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+        F: FnOnce(&V) -> bool,
+    {
+        let mut pred = Some(pred);
+        self.take_by(None, |val| match value.cmp(val.borrow()) {
+            cmp::Ordering::Equal => pred
+                .take()
+                .is_some_and(|pred| pred(val))
+                .then_some(cmp::Ordering::Equal),
+            order => Some(order),
+        })
     }
 
-    pub fn contains(&self, value: &V) -> bool {
-        match self.root {
-            None => false,
-            Some(ref node) => match value.cmp(&node.val) {
-                std::cmp::Ordering::Less => node.left.contains(value),
-                std::cmp::Ordering::Greater => node.right.contains(value),
-                std::cmp::Ordering::Equal => true,
-            },
+    /// Removes the value `search` reports as `Equal`, descending left on
+    /// `Less` and right on `Greater`. `None` stops without removing. As in
+    /// `insert_with`, the tree is only taken apart once `search` is done.
+    fn take_by<F>(&mut self, augment: Option<Augment<'_, V>>, mut search: F) -> Option<V>
+    where
+        F: FnMut(&V) -> Option<cmp::Ordering>,
+    {
+        let mut directions = Vec::new();
+        let mut cur = &self.root;
+
+        loop {
+            let node = cur.as_ref()?;
+            self.counters.record_comparison();
+            match search(&node.val)? {
+                cmp::Ordering::Less => {
+                    directions.push(Direction::Left);
+                    cur = &node.left;
+                }
+                cmp::Ordering::Greater => {
+                    directions.push(Direction::Right);
+                    cur = &node.right;
+                }
+                cmp::Ordering::Equal => break,
+            }
+        }
+
+        let (path, found) = self.detach(&directions, Vec::with_capacity(directions.len()));
+        let mut touched = Touched::new(augment.is_some());
+        let Some(mut node) = found else {
+            self.root = self.unwind(path, None, false, &mut touched);
+            return None;
+        };
+
+        let replacement = match (node.left.take(), node.right.take()) {
+            (None, None) => None,
+            (Some(child), None) | (None, Some(child)) => Some(child),
+            (Some(lnode), Some(rnode)) => {
+                let (mut new_node, right) = self.take_min_node(rnode, &mut touched);
+
+                new_node.left = Some(lnode);
+                new_node.right = right;
+                new_node.update_height();
+                self.counters.record_height_update();
+
+                let mut new_node = self.rebalance(new_node);
+                touched.push_rebalanced(&mut new_node);
+                Some(new_node)
+            }
+        };
+
+        self.root = self.unwind(path, replacement, true, &mut touched);
+        self.flush_events();
+        self.finish_touched(&touched, augment, "remove");
+        let val = self.recycle_node(node);
+        self.notify(Event::Removed(&val));
+        self.record_snapshot();
+
+        if self.self_check_level() == SelfCheck::Full {
+            self.run_self_check("remove");
         }
+
+        Some(val)
     }
 
-    fn get_height(&self) -> Height {
-        self.root.as_ref().map_or(0, |node| node.height)
+    /// The link reached by following `directions` from the root.
+    fn link_at(&mut self, directions: &[Direction]) -> &mut Link<V> {
+        let mut cur = &mut self.root;
+
+        for direction in directions {
+            match cur {
+                Some(node) => {
+                    cur = match direction {
+                        Direction::Left => &mut node.left,
+                        Direction::Right => &mut node.right,
+                    }
+                }
+                None => break,
+            }
+        }
+
+        cur
     }
 
-    fn get_val(&self) -> Option<&V> {
-        self.root.as_ref().map(|node| &node.val)
+    /// Takes the nodes along `directions` out of the tree, returning them
+    /// with the link the directions end at. Runs no user code, so nothing
+    /// can panic while the path is detached.
+    fn detach(&mut self, directions: &[Direction], mut path: Path<V>) -> (Path<V>, Link<V>) {
+        let mut directions = directions.iter();
+        let mut cur = self.root.take();
+
+        while let Some(mut node) = cur {
+            let Some(&direction) = directions.next() else {
+                return (path, Some(node));
+            };
+            cur = match direction {
+                Direction::Left => node.left.take(),
+                Direction::Right => node.right.take(),
+            };
+            path.push((node, direction));
+        }
+
+        (path, None)
     }
 
-    fn rotate_left(&mut self) {
-        let mut x = self.root.take().expect("Can't rotate left: root is empty");
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(value).is_some()
+    }
 
-        let mut y = x
-            .right
-            .root
-            .take()
-            .expect("Can't rotate left: no right child");
-        let t2 = y.left.root.take();
+    pub fn get<Q>(&self, value: &Q) -> Option<&V>
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut cur = &self.root;
 
-        x.right.root = t2;
-        x.update_height();
-        y.left.root.replace(x);
-        y.update_height();
+        while let Some(ref node) = cur {
+            self.counters.record_comparison();
+            match value.cmp(node.val.borrow()) {
+                cmp::Ordering::Less => cur = &node.left,
+                cmp::Ordering::Greater => cur = &node.right,
+                cmp::Ordering::Equal => return Some(&node.val),
+            }
+        }
 
-        self.root.replace(y);
+        None
     }
 
-    fn rotate_right(&mut self) {
-        let mut y = self.root.take().expect("Can't rotate right: root is empty");
+    /// The first value for which `pred` is false, given that `pred` holds
+    /// for a prefix of the values in order and fails for the rest.
+    pub fn partition_point<F: FnMut(&V) -> bool>(&self, mut pred: F) -> Option<&V> {
+        let mut first = None;
+        let mut cur = &self.root;
+
+        while let Some(ref node) = cur {
+            if pred(&node.val) {
+                cur = &node.right;
+            } else {
+                first = Some(&node.val);
+                cur = &node.left;
+            }
+        }
 
-        let mut x = y
-            .left
-            .root
-            .take()
-            .expect("Can't rotate right: no left child");
-        let t2 = x.right.root.take();
+        first
+    }
 
-        y.left.root = t2;
-        y.update_height();
-        x.right.root.replace(y);
-        x.update_height();
+    // Callers must not change how the element orders relative to others.
+    fn get_mut_unordered<Q>(&mut self, value: &Q) -> Option<&mut V>
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut cur = &mut self.root;
 
-        self.root.replace(x);
-    }
-
-    fn balance(&mut self, value: &V) {
-        match self.root {
-            None => (),
-            Some(ref mut node) => match node.get_balance() {
-                2.. => {
-                    let left_val = node
-                        .left
-                        .get_val()
-                        .expect("balance: left does not exist, but bal > 1");
-                    match value.cmp(left_val) {
-                        cmp::Ordering::Less => {
-                            self.rotate_right();
-                        }
-                        cmp::Ordering::Greater => {
-                            node.left.rotate_left();
-                            self.rotate_right();
-                        }
-                        _ => panic!("balance: new value and node value are equal: not allowed"),
-                    }
-                }
-                ..-1 => {
-                    let right_val = node
-                        .right
-                        .get_val()
-                        .expect("balance: right does not exist, but bal < -1");
-                    match value.cmp(right_val) {
-                        cmp::Ordering::Less => {
-                            node.right.rotate_right();
-                            self.rotate_left();
-                        }
-                        cmp::Ordering::Greater => {
-                            self.rotate_left();
-                        }
-                        _ => panic!("balance: new value and node value are equal: not allowed"),
-                    }
-                }
-                -1..=1 => (),
-            },
+        while let Some(ref mut node) = cur {
+            match value.cmp(node.val.borrow()) {
+                cmp::Ordering::Less => cur = &mut node.left,
+                cmp::Ordering::Greater => cur = &mut node.right,
+                cmp::Ordering::Equal => return Some(&mut node.val),
+            }
         }
+
+        None
     }
 
-    fn remove_balance(&mut self) {
-        match self.root {
-            None => (),
-            Some(ref mut node) => match node.get_balance() {
-                2.. => match node.left.get_balance() {
-                    ..=-1 => {
-                        node.left.rotate_left();
-                        self.rotate_right();
-                    }
-                    0.. => {
-                        self.rotate_right();
-                    }
-                },
-                ..-1 => match node.right.get_balance() {
-                    ..=0 => {
-                        self.rotate_left();
-                    }
-                    1.. => {
-                        node.right.rotate_right();
-                        self.rotate_left();
-                    }
-                },
-                -1..=1 => (),
-            },
+    fn alloc_node(&mut self, value: V) -> Box<AvlTreeNode<V>> {
+        match self.pop_free_node() {
+            Some(slot) => Box::write(slot, AvlTreeNode::new(value)),
+            None => Box::new(AvlTreeNode::new(value)),
         }
     }
 
-    fn get_balance(&self) -> isize {
-        self.root.as_ref().map_or(0, |n| (*n).get_balance())
-    }
-
-    fn take_min_node(&mut self) -> Box<AvlTreeNode<V>> {
-        match self.root.as_mut() {
-            None => panic!("take_min: too low"),
-            Some(node) => match node.left.root.as_ref() {
-                None => self.root.take().expect("take_min: should exist now"),
-                Some(_) => {
-                    let retval = node.left.take_min_node();
-                    node.update_height();
-                    self.remove_balance();
-                    retval
-                }
-            },
+    fn recycle_node(&mut self, node: Box<AvlTreeNode<V>>) -> V {
+        debug_assert!(node.left.is_none() && node.right.is_none());
+
+        let Some(extras) = self
+            .extras
+            .as_mut()
+            .filter(|extras| extras.free_nodes.len() < extras.free_list_limit)
+        else {
+            return node.val;
+        };
+
+        let raw = Box::into_raw(node);
+        // SAFETY: `raw` comes from a live box. The value is moved out exactly
+        // once, the links are empty so nothing else needs dropping, and
+        // MaybeUninit<T> has the same layout as T, so the allocation can be
+        // owned as uninitialized memory from here on.
+        unsafe {
+            let val = ptr::read(ptr::addr_of!((*raw).val));
+            extras
+                .free_nodes
+                .push(Box::from_raw(raw.cast::<MaybeUninit<AvlTreeNode<V>>>()));
+            val
         }
     }
-}
 
-impl<V: Ord + Copy> AvlTree<V> {
-    pub fn insert(&mut self, value: V) {
-        match self.root {
-            None => {
-                self.root.replace(Box::new(AvlTreeNode::new(value)));
-                return;
+    fn extras_mut(&mut self) -> &mut Extras<V> {
+        self.extras.get_or_insert_with(Box::default)
+    }
+
+    fn pop_free_node(&mut self) -> Option<Box<MaybeUninit<AvlTreeNode<V>>>> {
+        self.extras.as_mut()?.free_nodes.pop()
+    }
+
+    #[cfg(feature = "std")]
+    fn walk<F: FnMut(&V)>(&self, f: &mut F) {
+        walk(&self.root, f);
+    }
+
+    fn from_link(root: Link<V>) -> AvlTree<V> {
+        let mut tree = AvlTree::new();
+        tree.root = root;
+        tree
+    }
+
+    fn from_sorted<I: Iterator<Item = V>>(values: &mut I, len: usize) -> AvlTree<V> {
+        AvlTree::from_link(AvlTreeNode::from_sorted(values, len))
+    }
+
+    /// A changed height implies a changed size, and the size keeps
+    /// changing up to the root even once the heights stop.
+    ///
+    /// Runs no user code. Every node it restructures is added to `touched`,
+    /// to be checked and augmented once the tree is whole again.
+    fn unwind(
+        &mut self,
+        mut path: Path<V>,
+        mut child: Link<V>,
+        mut height_changed: bool,
+        touched: &mut Touched<V>,
+    ) -> Link<V> {
+        let size_changed = height_changed;
+
+        while let Some((mut node, direction)) = path.pop() {
+            match direction {
+                Direction::Left => node.left = child,
+                Direction::Right => node.right = child,
             }
-            Some(ref mut node) => {
-                match value.cmp(&node.val) {
-                    cmp::Ordering::Less => {
-                        node.left.insert(value);
-                    }
-                    cmp::Ordering::Greater => {
-                        node.right.insert(value);
-                    }
-                    cmp::Ordering::Equal => return,
-                };
 
+            if height_changed {
+                let old_height = node.height;
                 node.update_height();
+                self.counters.record_height_update();
+                node = self.rebalance(node);
+                height_changed = node.height != old_height;
+
+                let balance = node.get_balance();
+                if balance.abs() > self.max_imbalance() as isize {
+                    self.note_corruption(&path, balance);
+                }
+                touched.push_rebalanced(&mut node);
+            } else if size_changed {
+                node.update_size();
+                touched.push(&mut node);
             }
+
+            child = Some(node);
         }
 
-        self.balance(&value);
+        child
     }
-}
 
-impl<V: fmt::Display> AvlTree<V> {
-    fn get_level_string(&self, descend_by: usize, level: usize, node_str_width: usize) -> String {
-        match self.root {
-            None => match descend_by {
-                0 => format!("{:node_str_width$}", ""),
-                _ => {
-                    let space_between_nodes = (2_usize.pow(level as u32) - 1) * node_str_width;
-                    format!(
-                        "{}{:space_between_nodes$}{}",
-                        self.get_level_string(descend_by - 1, level, node_str_width),
-                        "",
-                        self.get_level_string(descend_by - 1, level, node_str_width)
-                    )
-                }
-            },
-            Some(ref node) => match descend_by {
-                0 => {
-                    format!("{:^node_str_width$}", format!("{}", node))
-                }
-                _ => {
-                    let space_between_nodes = (2_usize.pow(level as u32) - 1) * node_str_width;
-                    format!(
-                        "{}{:space_between_nodes$}{}",
-                        node.left
-                            .get_level_string(descend_by - 1, level, node_str_width),
-                        "",
-                        node.right
-                            .get_level_string(descend_by - 1, level, node_str_width)
-                    )
-                }
-            },
+    /// Keeps the first node that rebalancing could not fix for
+    /// [`AvlTree::try_validate`], so release builds notice it as well.
+    #[cold]
+    fn note_corruption(&mut self, path: &Path<V>, balance: isize) {
+        let extras = self.extras_mut();
+        if extras.corruption.is_none() {
+            extras.corruption = Some(InvariantError::Unbalanced {
+                path: path.iter().map(|&(_, dir)| dir.side()).collect(),
+                balance,
+            });
         }
     }
+
+    fn finish_touched(
+        &mut self,
+        touched: &Touched<V>,
+        augment: Option<Augment<'_, V>>,
+        operation: &str,
+    ) {
+        if let Some(augment) = augment {
+            for node in &touched.nodes {
+                // SAFETY: the nodes are boxed and still in the tree, and no
+                // other reference into it is live while `self` is borrowed
+                // mutably.
+                let AvlTreeNode {
+                    val, left, right, ..
+                } = unsafe { &mut *node.as_ptr() };
+                augment(
+                    val,
+                    left.as_deref().map(|left| &left.val),
+                    right.as_deref().map(|right| &right.val),
+                );
+            }
+        }
+        self.check_touched(&touched.nodes, operation);
+    }
+
+    /// Rotates only once the balance factor exceeds the tree's limit.
+    fn rebalance(&mut self, node: Box<AvlTreeNode<V>>) -> Box<AvlTreeNode<V>> {
+        if node.get_balance().abs() <= self.max_imbalance() as isize {
+            return node;
+        }
+
+        self.counters.record_rebalance(&node);
+        self.notify_rebalance(&node);
+        node.rebalance()
+    }
+
+    fn take_min_node(
+        &mut self,
+        mut node: Box<AvlTreeNode<V>>,
+        touched: &mut Touched<V>,
+    ) -> (Box<AvlTreeNode<V>>, Option<Box<AvlTreeNode<V>>>) {
+        let mut path = Vec::new();
+
+        while let Some(lnode) = node.left.take() {
+            path.push((node, Direction::Left));
+            node = lnode;
+        }
+
+        let rest = node.right.take();
+        node.update_height();
+        self.counters.record_height_update();
+
+        (node, self.unwind(path, rest, true, touched))
+    }
 }
 
 impl<V: Ord + fmt::Display> fmt::Display for AvlTree<V> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.root {
-            None => writeln!(f),
-            Some(ref node) => {
-                let node_char_width = format!(
-                    "{}",
-                    self.max().expect("No max even though root node exists")
-                )
-                .len();
-                let mut tree = String::new();
-                for descend_by in 0..node.height {
-                    let initial_space = (2_usize.pow((node.height - descend_by - 1) as u32)
-                        - 1)
-                        * node_char_width;
-                    tree.push_str(&format!(
-                        "{:initial_space$}{}\n",
-                        "",
-                        &self.get_level_string(
-                            descend_by,
-                            node.height - descend_by,
-                            node_char_width
-                        )
-                    ));
-                }
-                write!(f, "{}", tree)
-            }
-        }
+        render::write_tree(self.root.as_deref(), &DisplayOptions::default(), f)
     }
 }
 
@@ -321,14 +718,14 @@ impl<V: Ord> IntoIterator for AvlTree<V> {
 
         loop {
             while let Some(mut node) = cur_node {
-                cur_node = node.left.root.take();
+                cur_node = node.left.take();
                 stack.push(*node);
             }
 
             match stack.pop() {
                 Some(mut node) => {
                     queue.push(node.val);
-                    cur_node = node.right.root.take();
+                    cur_node = node.right.take();
                 }
                 None => match cur_node {
                     Some(_) => continue,
@@ -341,36 +738,207 @@ impl<V: Ord> IntoIterator for AvlTree<V> {
     }
 }
 
-type Height = usize;
+impl<V: Ord> From<BTreeSet<V>> for AvlTree<V> {
+    fn from(set: BTreeSet<V>) -> Self {
+        let len = set.len();
+        AvlTree::from_sorted(&mut set.into_iter(), len)
+    }
+}
+
+impl<V: Ord> From<AvlTree<V>> for BTreeSet<V> {
+    fn from(tree: AvlTree<V>) -> Self {
+        tree.into_iter().collect()
+    }
+}
+
+type Height = u8;
+
+/// A child link. This is a single nullable pointer, laid out exactly like
+/// `Option<NonNull<AvlTreeNode<V>>>`, and following it takes the same
+/// null check; `Box` adds ownership and drop, not an indirection.
+type Link<V> = Option<Box<AvlTreeNode<V>>>;
+
+type Path<V> = Vec<(Box<AvlTreeNode<V>>, Direction)>;
+
+/// Recomputes the summary kept in a node's value from the values of its
+/// children, see [`AvlTree::insert_augmented`].
+type Augment<'a, V> = &'a mut dyn FnMut(&mut V, Option<&V>, Option<&V>);
+
+/// The nodes restructured by one insert or remove, children before their
+/// parents. They are collected in debug builds, to be checked, and for
+/// augmented mutations. Boxed nodes never move, so the pointers stay valid
+/// until the tree is next mutated.
+struct Touched<V> {
+    augmented: bool,
+    nodes: Vec<NonNull<AvlTreeNode<V>>>,
+}
+
+impl<V> Touched<V> {
+    fn new(augmented: bool) -> Touched<V> {
+        Touched {
+            augmented,
+            nodes: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, node: &mut AvlTreeNode<V>) {
+        if self.augmented {
+            self.nodes.push(NonNull::from(node));
+        } else if cfg!(debug_assertions) && self.nodes.try_reserve(1).is_ok() {
+            // The debug check is best effort, so that `try_insert` does not
+            // abort on allocation failure.
+            self.nodes.push(NonNull::from(node));
+        }
+    }
+
+    /// A rotation also changes the children of the new top node.
+    fn push_rebalanced(&mut self, node: &mut AvlTreeNode<V>) {
+        if let Some(left) = node.left.as_deref_mut() {
+            self.push(left);
+        }
+        if let Some(right) = node.right.as_deref_mut() {
+            self.push(right);
+        }
+        self.push(node);
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    Left,
+    Right,
+}
+
+impl Direction {
+    fn side(self) -> Side {
+        match self {
+            Direction::Left => Side::Left,
+            Direction::Right => Side::Right,
+        }
+    }
+}
 
 struct AvlTreeNode<V> {
     val: V,
     height: Height,
-    left: AvlTree<V>,
-    right: AvlTree<V>,
+    size: usize,
+    left: Link<V>,
+    right: Link<V>,
 }
 
-impl<V: Ord> AvlTreeNode<V> {
+impl<V> AvlTreeNode<V> {
     fn new(value: V) -> AvlTreeNode<V> {
         AvlTreeNode {
             val: value,
             height: 1,
-            left: AvlTree::new(),
-            right: AvlTree::new(),
+            size: 1,
+            left: None,
+            right: None,
         }
     }
 
+    fn from_sorted<I: Iterator<Item = V>>(values: &mut I, len: usize) -> Link<V> {
+        if len == 0 {
+            return None;
+        }
+
+        let left = AvlTreeNode::from_sorted(values, len / 2);
+        // Running out early only leaves a smaller, still balanced tree.
+        let Some(val) = values.next() else {
+            return left;
+        };
+        let right = AvlTreeNode::from_sorted(values, len - len / 2 - 1);
+
+        let mut node = AvlTreeNode {
+            val,
+            height: 1,
+            size: 1,
+            left,
+            right,
+        };
+        node.update_height();
+
+        Some(Box::new(node))
+    }
+
+    /// Also refreshes the subtree size, since both follow from the
+    /// children.
     fn update_height(&mut self) {
-        self.height = 1 + cmp::max(self.left.get_height(), self.right.get_height());
+        self.height = 1 + cmp::max(get_height(&self.left), get_height(&self.right));
+        self.update_size();
+    }
+
+    fn update_size(&mut self) {
+        self.size = 1 + get_size(&self.left) + get_size(&self.right);
     }
 
     fn get_balance(&self) -> isize {
-        self.left.get_height() as isize - self.right.get_height() as isize
+        get_height(&self.left) as isize - get_height(&self.right) as isize
+    }
+
+    // The rotations and `rebalance` leave a node they cannot restructure
+    // as it is instead of panicking; `check_invariants` reports the result.
+    fn rotate_left(mut self: Box<Self>) -> Box<Self> {
+        let Some(mut y) = self.right.take() else {
+            debug_assert!(false, "Can't rotate left: no right child");
+            return self;
+        };
+        let t2 = y.left.take();
+
+        self.right = t2;
+        self.update_height();
+        y.left = Some(self);
+        y.update_height();
+
+        y
+    }
+
+    fn rotate_right(mut self: Box<Self>) -> Box<Self> {
+        let Some(mut x) = self.left.take() else {
+            debug_assert!(false, "Can't rotate right: no left child");
+            return self;
+        };
+        let t2 = x.right.take();
+
+        self.left = t2;
+        self.update_height();
+        x.right = Some(self);
+        x.update_height();
+
+        x
+    }
+
+    fn rebalance(mut self: Box<Self>) -> Box<Self> {
+        match self.get_balance() {
+            2.. => {
+                let Some(left) = self.left.take() else {
+                    debug_assert!(false, "rebalance: left does not exist, but bal > 1");
+                    return self;
+                };
+                self.left = Some(match left.get_balance() {
+                    ..=-1 => left.rotate_left(),
+                    0.. => left,
+                });
+                self.rotate_right()
+            }
+            ..-1 => {
+                let Some(right) = self.right.take() else {
+                    debug_assert!(false, "rebalance: right does not exist, but bal < -1");
+                    return self;
+                };
+                self.right = Some(match right.get_balance() {
+                    1.. => right.rotate_right(),
+                    ..=0 => right,
+                });
+                self.rotate_left()
+            }
+            -1..=1 => self,
+        }
     }
 
     fn min(&self) -> &V {
         let mut min_node = self;
-        while let Some(ref l_node) = min_node.left.root {
+        while let Some(ref l_node) = min_node.left {
             min_node = l_node;
         }
         &min_node.val
@@ -378,7 +946,7 @@ impl<V: Ord> AvlTreeNode<V> {
 
     fn max(&self) -> &V {
         let mut max_node = self;
-        while let Some(ref r_node) = max_node.right.root {
+        while let Some(ref r_node) = max_node.right {
             max_node = r_node;
         }
         &max_node.val
@@ -390,3 +958,313 @@ impl<V: fmt::Display> fmt::Display for AvlTreeNode<V> {
         write!(f, "{}", self.val)
     }
 }
+
+fn get_height<V>(link: &Link<V>) -> Height {
+    link.as_ref().map_or(0, |node| node.height)
+}
+
+fn get_size<V>(link: &Link<V>) -> usize {
+    link.as_ref().map_or(0, |node| node.size)
+}
+
+fn walk<V, F: FnMut(&V)>(link: &Link<V>, f: &mut F) {
+    if let Some(ref node) = link {
+        walk(&node.left, f);
+        f(&node.val);
+        walk(&node.right, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::{format, string::String, vec::Vec};
+    use core::{cell::Cell, cmp};
+    use std::panic::{self, AssertUnwindSafe};
+
+    use crate::{AvlTree, AvlTreeBuilder, DuplicatePolicy, SelfCheck};
+
+    /// Compares by `key`, panicking whenever either side is poisoned.
+    #[derive(Debug)]
+    struct Key {
+        key: u32,
+        poisoned: bool,
+    }
+
+    impl Key {
+        fn new(key: u32) -> Key {
+            Key {
+                key,
+                poisoned: false,
+            }
+        }
+    }
+
+    impl PartialEq for Key {
+        fn eq(&self, other: &Self) -> bool {
+            self.cmp(other) == cmp::Ordering::Equal
+        }
+    }
+
+    impl Eq for Key {}
+
+    impl PartialOrd for Key {
+        fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Key {
+        fn cmp(&self, other: &Self) -> cmp::Ordering {
+            assert!(!self.poisoned && !other.poisoned, "poisoned comparison");
+            self.key.cmp(&other.key)
+        }
+    }
+
+    fn tree_of(values: impl IntoIterator<Item = u32>) -> AvlTree<u32> {
+        let mut tree = AvlTree::new();
+        for value in values {
+            tree.insert(value);
+        }
+        tree
+    }
+
+    fn contents<V: Ord + Copy>(tree: &AvlTree<V>) -> Vec<V> {
+        tree.iter().copied().collect()
+    }
+
+    #[test]
+    fn panicking_comparison_leaves_tree_intact() {
+        let mut tree = AvlTree::new();
+        for key in 0..1000 {
+            tree.insert(Key::new(key));
+        }
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            tree.insert(Key {
+                key: 500,
+                poisoned: true,
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(tree.len(), 1000);
+        assert_eq!(tree.check_invariants(), Ok(()));
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            tree.remove(&Key {
+                key: 500,
+                poisoned: true,
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(tree.len(), 1000);
+        assert_eq!(tree.check_invariants(), Ok(()));
+    }
+
+    std::thread_local! {
+        static COMPARISONS_LEFT: Cell<usize> = const { Cell::new(usize::MAX) };
+    }
+
+    /// Compares like `u32`, panicking once `COMPARISONS_LEFT` runs out.
+    #[derive(Debug, PartialEq, Eq)]
+    struct Budgeted(u32);
+
+    impl PartialOrd for Budgeted {
+        fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Budgeted {
+        fn cmp(&self, other: &Self) -> cmp::Ordering {
+            let left = COMPARISONS_LEFT.get();
+            assert!(left > 0, "comparison budget exhausted");
+            COMPARISONS_LEFT.set(left - 1);
+            self.0.cmp(&other.0)
+        }
+    }
+
+    #[test]
+    fn panicking_comparison_during_self_check_leaves_tree_intact() {
+        let mut tree = AvlTreeBuilder::new().self_check(SelfCheck::Full).build();
+        tree.set_observer(|_| {});
+        for key in (0..200).step_by(2) {
+            tree.insert(Budgeted(key));
+        }
+
+        // Allow exactly the comparisons of the search, so the first one
+        // after it, in the check of the rebalanced nodes, panics.
+        let probe = Budgeted(101);
+        COMPARISONS_LEFT.set(usize::MAX);
+        tree.contains(&probe);
+        COMPARISONS_LEFT.set(usize::MAX - COMPARISONS_LEFT.get());
+        let result = panic::catch_unwind(AssertUnwindSafe(|| tree.insert(probe)));
+        COMPARISONS_LEFT.set(usize::MAX);
+        assert!(result.is_err());
+        assert_eq!(tree.len(), 101);
+        assert!(tree.contains(&Budgeted(101)));
+        assert_eq!(tree.check_invariants(), Ok(()));
+
+        COMPARISONS_LEFT.set(usize::MAX);
+        tree.contains(&Budgeted(100));
+        COMPARISONS_LEFT.set(usize::MAX - COMPARISONS_LEFT.get());
+        let result = panic::catch_unwind(AssertUnwindSafe(|| tree.remove(&Budgeted(100))));
+        COMPARISONS_LEFT.set(usize::MAX);
+        assert!(result.is_err());
+        assert_eq!(tree.len(), 100);
+        assert!(!tree.contains(&Budgeted(100)));
+        assert_eq!(tree.check_invariants(), Ok(()));
+
+        assert!(tree.insert(Budgeted(100)));
+        assert!(tree.remove(&Budgeted(101)));
+        assert_eq!(tree.len(), 100);
+    }
+
+    #[test]
+    fn panicking_remove_if_predicate_leaves_tree_intact() {
+        let mut tree = tree_of(0..1000);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            tree.remove_if(&700, |_| panic!("predicate"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(contents(&tree), (0..1000).collect::<Vec<_>>());
+        assert_eq!(tree.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn removing_an_absent_value_reports_false() {
+        let mut tree = tree_of([10, 20, 30]);
+
+        assert!(!tree.remove(&15));
+        assert!(!tree.remove(&40));
+        assert_eq!(contents(&tree), [10, 20, 30]);
+    }
+
+    #[test]
+    fn insert_reports_whether_the_value_was_new() {
+        let mut tree = tree_of([10, 20]);
+        assert!(tree.insert(30));
+        assert!(!tree.insert(20));
+
+        let mut tree: AvlTree<u32> = AvlTreeBuilder::new()
+            .duplicates(DuplicatePolicy::Replace)
+            .build();
+        assert!(tree.insert(1));
+        assert!(!tree.insert(1));
+        assert_eq!(tree.try_insert(2), Ok(true));
+        assert_eq!(contents(&tree), [1, 2]);
+    }
+
+    #[test]
+    fn links_are_single_nullable_pointers() {
+        use core::{mem::size_of, ptr::NonNull};
+
+        use crate::{AvlTreeNode, Counters, Link};
+
+        assert_eq!(size_of::<Link<u64>>(), size_of::<*const u8>());
+        assert_eq!(
+            size_of::<Link<u64>>(),
+            size_of::<Option<NonNull<AvlTreeNode<u64>>>>()
+        );
+        assert_eq!(
+            size_of::<AvlTree<u64>>(),
+            size_of::<Link<u64>>() + size_of::<Counters>() + size_of::<*const u8>()
+        );
+    }
+
+    #[test]
+    fn display_measures_values_in_chars() {
+        let render = |values: [&str; 4]| {
+            let mut tree = AvlTree::new();
+            for value in values {
+                tree.insert(String::from(value));
+            }
+            format!("{}", tree)
+        };
+
+        let ascii = render(["bb", "a", "ccc", "d"]);
+        let accented = render(["éé", "à", "ööö", "ü"]);
+        let expected: String = ascii
+            .chars()
+            .map(|c| match c {
+                'a' => 'à',
+                'b' => 'é',
+                'c' => 'ö',
+                'd' => 'ü',
+                c => c,
+            })
+            .collect();
+        assert_eq!(accented, expected);
+    }
+
+    #[test]
+    fn removing_a_node_with_two_children_keeps_the_successors_subtree() {
+        // 4 has two children, and its successor 5 has a right child 6.
+        let mut tree = tree_of([4, 2, 7, 1, 3, 5, 8, 6]);
+
+        assert!(tree.remove(&4));
+        assert_eq!(contents(&tree), [1, 2, 3, 5, 6, 7, 8]);
+        assert_eq!(tree.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    #[cfg(not(feature = "metrics"))]
+    fn unconfigured_tree_is_two_pointers() {
+        assert_eq!(
+            core::mem::size_of::<AvlTree<u32>>(),
+            2 * core::mem::size_of::<usize>()
+        );
+
+        let tree: AvlTree<u32> = AvlTreeBuilder::new().build();
+        assert!(tree.extras.is_none());
+    }
+
+    #[test]
+    fn configuration_survives_out_of_line() {
+        let mut tree: AvlTree<u32> = AvlTreeBuilder::new()
+            .duplicates(DuplicatePolicy::Replace)
+            .max_imbalance(3)
+            .free_list_limit(4)
+            .build();
+        for value in 0..100 {
+            tree.insert(value);
+        }
+
+        assert_eq!(tree.duplicate_policy(), DuplicatePolicy::Replace);
+        assert_eq!(tree.free_list_limit(), 4);
+        let (less, _, greater) = tree.split3(&50);
+        assert_eq!((less.max_imbalance(), greater.max_imbalance()), (3, 3));
+    }
+
+    #[test]
+    fn reserved_nodes_are_released_once_used() {
+        let mut tree: AvlTree<u32> = AvlTree::with_capacity(64);
+        assert_eq!(tree.free_list_limit(), 0);
+        assert_eq!(tree.spare_nodes(), 64);
+
+        for value in 0..48 {
+            tree.insert(value);
+        }
+        assert_eq!(tree.spare_nodes(), 16);
+        for value in 0..48 {
+            tree.remove(&value);
+        }
+        assert_eq!(tree.spare_nodes(), 16);
+
+        tree.set_free_list_limit(8);
+        assert_eq!(tree.spare_nodes(), 8);
+        tree.reserve(32);
+        assert_eq!(tree.spare_nodes(), 32);
+        tree.shrink_to_fit();
+        assert_eq!((tree.spare_nodes(), tree.free_list_limit()), (0, 8));
+        for value in 0..20 {
+            tree.insert(value);
+        }
+        for value in 0..20 {
+            tree.remove(&value);
+        }
+        assert_eq!(tree.spare_nodes(), 8);
+    }
+}
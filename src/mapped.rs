@@ -0,0 +1,268 @@
+//! Memory-map compatible layout of a [`FrozenAvlTree`](crate::FrozenAvlTree) of plain-old-data
+//! values.
+//!
+//! The layout is a fixed 64-byte header followed directly by the values in
+//! ascending order, exactly as they are laid out in memory:
+//!
+//! | offset | size | content                                              |
+//! |--------|------|------------------------------------------------------|
+//! | 0      | 8    | magic bytes `AVLFROZ\0`                              |
+//! | 8      | 4    | format version, currently `1`                        |
+//! | 12     | 4    | `0x01020304`, detects an endianness mismatch         |
+//! | 16     | 4    | `size_of::<V>()`                                     |
+//! | 20     | 4    | `align_of::<V>()`                                    |
+//! | 24     | 8    | number of values `n`                                 |
+//! | 32     | 32   | zero padding                                         |
+//! | 64     | ...  | `n * size_of::<V>()` bytes of values                 |
+//!
+//! Header integers use the writer's native byte order. Because the values
+//! start at offset 64, a buffer that is itself 64-byte aligned (as `mmap`ed
+//! memory is) can be read in place without copying.
+
+use core::{borrow::Borrow, error::Error, fmt, mem, ops::RangeBounds, slice};
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+use crate::frozen;
+
+const MAGIC: &[u8; 8] = b"AVLFROZ\0";
+const VERSION: u32 = 1;
+const BYTE_ORDER_MARK: u32 = 0x0102_0304;
+const HEADER_LEN: usize = 64;
+
+/// # Safety
+///
+/// Implementors must have no padding bytes, no pointers and no invalid bit
+/// patterns, so that any properly aligned byte sequence of the right length
+/// is a valid value.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($t:ty),*) => {
+        $(
+            unsafe impl Pod for $t {}
+        )*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MappedLayoutError {
+    TooShort,
+    BadMagic,
+    UnsupportedVersion(u32),
+    ByteOrderMismatch,
+    ValueLayoutMismatch,
+    Misaligned,
+}
+
+impl fmt::Display for MappedLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MappedLayoutError::TooShort => write!(f, "buffer is shorter than its header claims"),
+            MappedLayoutError::BadMagic => write!(f, "buffer is not a frozen AVL tree"),
+            MappedLayoutError::UnsupportedVersion(v) => {
+                write!(f, "unsupported layout version {}", v)
+            }
+            MappedLayoutError::ByteOrderMismatch => {
+                write!(f, "buffer was written with a different byte order")
+            }
+            MappedLayoutError::ValueLayoutMismatch => {
+                write!(f, "value size or alignment does not match")
+            }
+            MappedLayoutError::Misaligned => write!(f, "values are not aligned in memory"),
+        }
+    }
+}
+
+impl Error for MappedLayoutError {}
+
+#[cfg(feature = "std")]
+impl<V: Pod + Ord> frozen::FrozenAvlTree<V> {
+    pub fn write_mapped<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let values = self.as_slice();
+
+        let mut header = [0_u8; HEADER_LEN];
+        header[0..8].copy_from_slice(MAGIC);
+        header[8..12].copy_from_slice(&VERSION.to_ne_bytes());
+        header[12..16].copy_from_slice(&BYTE_ORDER_MARK.to_ne_bytes());
+        header[16..20].copy_from_slice(&(mem::size_of::<V>() as u32).to_ne_bytes());
+        header[20..24].copy_from_slice(&(mem::align_of::<V>() as u32).to_ne_bytes());
+        header[24..32].copy_from_slice(&(values.len() as u64).to_ne_bytes());
+        writer.write_all(&header)?;
+
+        // SAFETY: `V: Pod` has no padding, so every byte of the slice is
+        // initialized and may be viewed as `u8`.
+        let bytes = unsafe {
+            slice::from_raw_parts(values.as_ptr().cast::<u8>(), mem::size_of_val(values))
+        };
+        writer.write_all(bytes)
+    }
+}
+
+pub struct MappedAvlTree<'a, V> {
+    values: &'a [V],
+}
+
+impl<'a, V: Pod + Ord> MappedAvlTree<'a, V> {
+    /// Only the header and buffer bounds are checked; the values are trusted
+    /// to be in strictly ascending order as written by `write_mapped`.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<MappedAvlTree<'a, V>, MappedLayoutError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(MappedLayoutError::TooShort);
+        }
+        if &bytes[0..8] != MAGIC {
+            return Err(MappedLayoutError::BadMagic);
+        }
+
+        let read_u32 = |at: usize| u32::from_ne_bytes(bytes[at..at + 4].try_into().unwrap());
+        let version = read_u32(8);
+        if version != VERSION {
+            return Err(MappedLayoutError::UnsupportedVersion(version));
+        }
+        if read_u32(12) != BYTE_ORDER_MARK {
+            return Err(MappedLayoutError::ByteOrderMismatch);
+        }
+        if read_u32(16) as usize != mem::size_of::<V>()
+            || read_u32(20) as usize != mem::align_of::<V>()
+        {
+            return Err(MappedLayoutError::ValueLayoutMismatch);
+        }
+
+        let len = usize::try_from(u64::from_ne_bytes(bytes[24..32].try_into().unwrap()))
+            .map_err(|_| MappedLayoutError::TooShort)?;
+        let data = &bytes[HEADER_LEN..];
+        match len.checked_mul(mem::size_of::<V>()) {
+            Some(data_len) if data_len <= data.len() => (),
+            _ => return Err(MappedLayoutError::TooShort),
+        }
+        if data.as_ptr().align_offset(mem::align_of::<V>()) != 0 {
+            return Err(MappedLayoutError::Misaligned);
+        }
+
+        // SAFETY: the data is in bounds and aligned for `V`, and `V: Pod`
+        // accepts any bit pattern.
+        let values = unsafe { slice::from_raw_parts(data.as_ptr().cast::<V>(), len) };
+
+        Ok(MappedAvlTree { values })
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn min(&self) -> Option<&'a V> {
+        self.values.first()
+    }
+
+    pub fn max(&self) -> Option<&'a V> {
+        self.values.last()
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        frozen::slice_contains(self.values, value)
+    }
+
+    pub fn rank<Q>(&self, value: &Q) -> usize
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        frozen::slice_rank(self.values, value)
+    }
+
+    pub fn select(&self, rank: usize) -> Option<&'a V> {
+        self.values.get(rank)
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> slice::Iter<'a, V>
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        frozen::slice_range(self.values, range).iter()
+    }
+
+    pub fn iter(&self) -> slice::Iter<'a, V> {
+        self.values.iter()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use alloc::vec::Vec;
+    use core::mem;
+
+    use crate::{AvlTree, MappedAvlTree, MappedLayoutError};
+
+    /// The frozen layout of `tree`, copied into 64-byte aligned storage as
+    /// an `mmap` would provide.
+    fn mapped_bytes(tree: &AvlTree<u64>) -> Vec<[u64; 8]> {
+        let mut bytes = Vec::new();
+        tree.snapshot().write_mapped(&mut bytes).unwrap();
+        let mut storage = alloc::vec![[0_u64; 8]; bytes.len().div_ceil(64)];
+        // SAFETY: `storage` has at least `bytes.len()` bytes, and any bytes
+        // are valid `u64`s.
+        unsafe {
+            let dst = storage.as_mut_ptr().cast::<u8>();
+            dst.copy_from_nonoverlapping(bytes.as_ptr(), bytes.len());
+        }
+        storage
+    }
+
+    fn as_bytes(storage: &[[u64; 8]]) -> &[u8] {
+        // SAFETY: plain integers, viewed as the bytes they occupy.
+        unsafe { core::slice::from_raw_parts(storage.as_ptr().cast(), mem::size_of_val(storage)) }
+    }
+
+    #[test]
+    fn values_round_trip_in_place() {
+        let mut tree = AvlTree::new();
+        for value in [30_u64, 10, 20, u64::MAX, 0] {
+            tree.insert(value);
+        }
+        let storage = mapped_bytes(&tree);
+        let mapped = MappedAvlTree::<u64>::from_bytes(as_bytes(&storage)).unwrap();
+
+        assert_eq!(mapped.len(), 5);
+        assert!(mapped.iter().eq(tree.iter()));
+        assert!(mapped.contains(&20));
+        assert_eq!(mapped.rank(&25), 3);
+        assert_eq!(mapped.select(4), Some(&u64::MAX));
+
+        let storage = mapped_bytes(&AvlTree::new());
+        let mapped = MappedAvlTree::<u64>::from_bytes(as_bytes(&storage)).unwrap();
+        assert!(mapped.is_empty());
+    }
+
+    #[test]
+    fn mismatched_layouts_are_rejected() {
+        let mut tree = AvlTree::new();
+        tree.insert(1_u64);
+        let storage = mapped_bytes(&tree);
+        let bytes = as_bytes(&storage);
+
+        assert_eq!(
+            MappedAvlTree::<u32>::from_bytes(bytes).err(),
+            Some(MappedLayoutError::ValueLayoutMismatch)
+        );
+        assert_eq!(
+            MappedAvlTree::<u64>::from_bytes(&bytes[..63]).err(),
+            Some(MappedLayoutError::TooShort)
+        );
+        assert_eq!(
+            MappedAvlTree::<u64>::from_bytes(&bytes[1..]).err(),
+            Some(MappedLayoutError::BadMagic)
+        );
+    }
+}
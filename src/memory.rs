@@ -0,0 +1,41 @@
+use alloc::boxed::Box;
+use core::mem;
+
+use crate::{walk, AvlTree, AvlTreeNode};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub nodes: usize,
+    pub node_bytes: usize,
+    pub spare_bytes: usize,
+    pub value_heap_bytes: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.node_bytes + self.spare_bytes + self.value_heap_bytes
+    }
+}
+
+impl<V: Ord> AvlTree<V> {
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.memory_usage_with(|_| 0)
+    }
+
+    pub fn memory_usage_with<F: FnMut(&V) -> usize>(&self, mut value_heap_bytes: F) -> MemoryUsage {
+        let node_size = mem::size_of::<AvlTreeNode<V>>();
+        let mut usage = MemoryUsage::default();
+
+        walk(&self.root, &mut |value| {
+            usage.nodes += 1;
+            usage.value_heap_bytes += value_heap_bytes(value);
+        });
+        usage.node_bytes = usage.nodes * node_size;
+        if let Some(ref extras) = self.extras {
+            usage.spare_bytes = extras.free_nodes.len() * node_size
+                + extras.free_nodes.capacity() * mem::size_of::<Box<AvlTreeNode<V>>>();
+        }
+
+        usage
+    }
+}
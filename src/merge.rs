@@ -0,0 +1,112 @@
+use alloc::{collections::BinaryHeap, vec::Vec};
+use core::cmp;
+
+use crate::AvlTree;
+
+/// The next value of one input to [`AvlTree::merge_all`], ordered so that
+/// the max-heap pops the smallest value, and among equal values the one
+/// from the earliest tree.
+struct Head<V> {
+    val: V,
+    source: usize,
+}
+
+impl<V: Ord> PartialEq for Head<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl<V: Ord> Eq for Head<V> {}
+
+impl<V: Ord> PartialOrd for Head<V> {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<V: Ord> Ord for Head<V> {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        (&other.val, other.source).cmp(&(&self.val, self.source))
+    }
+}
+
+impl<V: Ord> AvlTree<V> {
+    /// Merges the trees with a k-way merge and builds the result balanced
+    /// from the merged sequence, in O(n log k) for n values in k trees. Of
+    /// equal values, the one from the earliest tree is kept.
+    pub fn merge_all<I: IntoIterator<Item = AvlTree<V>>>(trees: I) -> AvlTree<V> {
+        let mut sources: Vec<_> = trees.into_iter().map(AvlTree::into_iter).collect();
+        let mut merged = Vec::with_capacity(sources.iter().map(ExactSizeIterator::len).sum());
+        let mut heap = BinaryHeap::with_capacity(sources.len());
+        for (source, values) in sources.iter_mut().enumerate() {
+            heap.extend(values.next().map(|val| Head { val, source }));
+        }
+
+        while let Some(Head { val, source }) = heap.pop() {
+            heap.extend(sources[source].next().map(|val| Head { val, source }));
+            if merged.last() != Some(&val) {
+                merged.push(val);
+            }
+        }
+
+        let len = merged.len();
+        AvlTree::from_sorted(&mut merged.into_iter(), len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::cmp;
+
+    use crate::AvlTree;
+
+    /// Ordered by `key` alone, so that it shows which tree a value came from.
+    #[derive(Debug)]
+    struct Tagged {
+        key: i32,
+        tree: usize,
+    }
+
+    impl PartialEq for Tagged {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+
+    impl Eq for Tagged {}
+
+    impl PartialOrd for Tagged {
+        fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Tagged {
+        fn cmp(&self, other: &Self) -> cmp::Ordering {
+            self.key.cmp(&other.key)
+        }
+    }
+
+    #[test]
+    fn merge_all_keeps_the_earliest_of_equal_values() {
+        let trees = [[1, 4, 7], [2, 4, 8], [4, 7, 9]]
+            .into_iter()
+            .enumerate()
+            .map(|(tree, keys)| {
+                let mut values = AvlTree::new();
+                for key in keys {
+                    values.insert(Tagged { key, tree });
+                }
+                values
+            });
+
+        let merged = AvlTree::merge_all(trees);
+        assert_eq!(
+            merged.iter().map(|v| (v.key, v.tree)).collect::<Vec<_>>(),
+            [(1, 0), (2, 1), (4, 0), (7, 0), (8, 1), (9, 2)]
+        );
+        assert!(AvlTree::<i32>::merge_all([]).is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+//! Mermaid flowchart export.
+//!
+//! Nodes are numbered in pre-order like the DOT export. Mermaid does not
+//! keep a lone child on its own side, so edges are labelled `L` or `R`.
+
+use alloc::string::{String, ToString};
+use core::fmt::{self, Write};
+
+use crate::{AvlTree, Link};
+
+impl<V: Ord + fmt::Display> AvlTree<V> {
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::new();
+        self.write_mermaid(&mut out)
+            .expect("writing to a String cannot fail");
+        out
+    }
+
+    pub fn write_mermaid<W: Write>(&self, out: &mut W) -> fmt::Result {
+        writeln!(out, "flowchart TD")?;
+        write_node(&self.root, out, &mut 0).map(|_| ())
+    }
+}
+
+fn write_node<V: fmt::Display, W: Write>(
+    link: &Link<V>,
+    out: &mut W,
+    next_id: &mut usize,
+) -> Result<Option<usize>, fmt::Error> {
+    let Some(node) = link else {
+        return Ok(None);
+    };
+
+    let id = *next_id;
+    *next_id += 1;
+
+    write!(out, "    n{}[\"", id)?;
+    write_escaped(&node.val.to_string(), out)?;
+    writeln!(out, "\"]")?;
+
+    for (child, side) in [(&node.left, 'L'), (&node.right, 'R')] {
+        if let Some(child_id) = write_node(child, out, next_id)? {
+            writeln!(out, "    n{} -->|{}| n{}", id, side, child_id)?;
+        }
+    }
+
+    Ok(Some(id))
+}
+
+fn write_escaped<W: Write>(label: &str, out: &mut W) -> fmt::Result {
+    for c in label.chars() {
+        match c {
+            '"' => out.write_str("#quot;")?,
+            '\n' => out.write_str("<br>")?,
+            _ => out.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::AvlTree;
+
+    #[test]
+    fn mermaid_output() {
+        let mut tree = AvlTree::new();
+        for value in [2, 1, 3] {
+            tree.insert(value);
+        }
+        assert_eq!(
+            tree.to_mermaid(),
+            "flowchart TD\n    n0[\"2\"]\n    n1[\"1\"]\n    n0 -->|L| n1\n    \
+             n2[\"3\"]\n    n0 -->|R| n2\n"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! Operation counters, collected only with the `metrics` feature.
+//!
+//! Without the feature [`Counters`] is empty and every `record_*` method
+//! compiles to nothing, so call sites need no `cfg` of their own.
+
+#[cfg(feature = "metrics")]
+use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+#[cfg(feature = "metrics")]
+use crate::AvlTree;
+use crate::AvlTreeNode;
+
+/// A snapshot of a tree's counters, see [`AvlTree::metrics`].
+///
+/// The rotation fields count rebalancing cases: LL and RR are a single
+/// rotation, LR and RL a double rotation. Comparisons are counted while
+/// searching in `insert`, `remove`, `get` and `contains`.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    pub ll_rotations: usize,
+    pub rr_rotations: usize,
+    pub lr_rotations: usize,
+    pub rl_rotations: usize,
+    pub comparisons: usize,
+    pub height_updates: usize,
+}
+
+#[cfg(feature = "metrics")]
+impl Metrics {
+    /// Single rotations performed, counting a double rotation as two.
+    pub fn rotations(&self) -> usize {
+        self.ll_rotations + self.rr_rotations + 2 * (self.lr_rotations + self.rl_rotations)
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    #[cfg(feature = "metrics")]
+    ll_rotations: AtomicUsize,
+    #[cfg(feature = "metrics")]
+    rr_rotations: AtomicUsize,
+    #[cfg(feature = "metrics")]
+    lr_rotations: AtomicUsize,
+    #[cfg(feature = "metrics")]
+    rl_rotations: AtomicUsize,
+    #[cfg(feature = "metrics")]
+    comparisons: AtomicUsize,
+    #[cfg(feature = "metrics")]
+    height_updates: AtomicUsize,
+}
+
+impl Counters {
+    #[inline]
+    pub(crate) fn record_comparison(&self) {
+        #[cfg(feature = "metrics")]
+        self.comparisons.fetch_add(1, Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn record_height_update(&self) {
+        #[cfg(feature = "metrics")]
+        self.height_updates.fetch_add(1, Relaxed);
+    }
+
+    /// Must be called before `node.rebalance()` to classify the case.
+    #[inline]
+    pub(crate) fn record_rebalance<V>(&self, node: &AvlTreeNode<V>) {
+        #[cfg(feature = "metrics")]
+        {
+            let child_balance =
+                |child: &crate::Link<V>| child.as_ref().map_or(0, |child| child.get_balance());
+            let counter = match node.get_balance() {
+                2.. if child_balance(&node.left) < 0 => &self.lr_rotations,
+                2.. => &self.ll_rotations,
+                ..-1 if child_balance(&node.right) > 0 => &self.rl_rotations,
+                ..-1 => &self.rr_rotations,
+                _ => return,
+            };
+            counter.fetch_add(1, Relaxed);
+        }
+        #[cfg(not(feature = "metrics"))]
+        let _ = node;
+    }
+
+    #[cfg(feature = "metrics")]
+    fn snapshot(&self) -> Metrics {
+        Metrics {
+            ll_rotations: self.ll_rotations.load(Relaxed),
+            rr_rotations: self.rr_rotations.load(Relaxed),
+            lr_rotations: self.lr_rotations.load(Relaxed),
+            rl_rotations: self.rl_rotations.load(Relaxed),
+            comparisons: self.comparisons.load(Relaxed),
+            height_updates: self.height_updates.load(Relaxed),
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl<V: Ord> AvlTree<V> {
+    pub fn metrics(&self) -> Metrics {
+        self.counters.snapshot()
+    }
+
+    pub fn reset_metrics(&mut self) {
+        self.counters = Counters::default();
+    }
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use crate::{AvlTree, Metrics};
+
+    #[test]
+    fn counts_rotations_and_comparisons() {
+        let mut tree = AvlTree::new();
+        // 3 needs a single (RR) rotation at 1, then 4 a double (RL) one at 3.
+        for value in [1, 2, 3, 5, 4] {
+            tree.insert(value);
+        }
+        let metrics = tree.metrics();
+        assert_eq!(
+            metrics,
+            Metrics {
+                ll_rotations: 0,
+                rr_rotations: 1,
+                lr_rotations: 0,
+                rl_rotations: 1,
+                comparisons: 8,
+                height_updates: 7,
+            }
+        );
+        assert_eq!(metrics.rotations(), 3);
+
+        tree.contains(&4);
+        assert_eq!(tree.metrics().comparisons, 10);
+        tree.reset_metrics();
+        assert_eq!(tree.metrics(), Metrics::default());
+    }
+}
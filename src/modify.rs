@@ -0,0 +1,76 @@
+use core::{borrow::Borrow, cmp, ptr};
+
+use crate::AvlTree;
+
+impl<V: Ord> AvlTree<V> {
+    /// Calls `f` with mutable access to the value equal to `value` and
+    /// returns whether there was one.
+    ///
+    /// `f` is meant to change data that does not take part in the ordering.
+    /// If the value no longer compares equal to `value` afterwards, it is
+    /// taken out and inserted again at its new position. Should an equal
+    /// value already be there, the modified value stays out of the tree
+    /// and is returned as the error, whatever the duplicate policy.
+    pub fn modify<Q, F>(&mut self, value: &Q, f: F) -> Result<bool, V>
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+        F: FnOnce(&mut V),
+    {
+        let Some(stored) = self.get_mut_unordered(value) else {
+            return Ok(false);
+        };
+
+        f(stored);
+        if (*stored).borrow() == value {
+            return Ok(true);
+        }
+
+        // Every other node still orders as before, so searching for `value`
+        // leads to the modified node, which is recognized by its address.
+        let target: *const V = stored;
+        let moved = self.take_by(None, |val| {
+            if ptr::eq(val, target) {
+                Some(cmp::Ordering::Equal)
+            } else {
+                Some(value.cmp(val.borrow()))
+            }
+        });
+        if let Some(moved) = moved {
+            if self.contains::<V>(&moved) {
+                return Err(moved);
+            }
+            self.insert(moved);
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::AvlTree;
+
+    fn contents(tree: &AvlTree<u32>) -> Vec<u32> {
+        tree.iter().copied().collect()
+    }
+
+    #[test]
+    fn modified_values_move_or_are_handed_back() {
+        let mut tree = AvlTree::new();
+        for value in 0..10 {
+            tree.insert(value * 10);
+        }
+
+        assert_eq!(tree.modify(&5, |v| *v += 1), Ok(false));
+        assert_eq!(tree.modify(&20, |_| {}), Ok(true));
+        assert_eq!(tree.modify(&20, |v| *v = 55), Ok(true));
+        assert_eq!(contents(&tree), [0, 10, 30, 40, 50, 55, 60, 70, 80, 90]);
+
+        // 30 becomes 70, which is already present.
+        assert_eq!(tree.modify(&30, |v| *v = 70), Err(70));
+        assert_eq!(contents(&tree), [0, 10, 40, 50, 55, 60, 70, 80, 90]);
+        assert_eq!(tree.check_invariants(), Ok(()));
+    }
+}
@@ -0,0 +1,258 @@
+use alloc::boxed::Box;
+use core::{mem, ptr::NonNull};
+
+use crate::{AvlTree, AvlTreeNode, Extras, Side};
+
+/// A structural change reported to the observer set with
+/// [`AvlTree::set_observer`].
+///
+/// Events are delivered once the operation has put the tree back
+/// together, in the order the changes happened, so an observer that panics
+/// cannot lose any nodes. `Rotated` names the value of the node that moved
+/// down and the direction it moved in. A double rotation is reported as its
+/// two single rotations, child first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event<'a, V> {
+    Inserted(&'a V),
+    Removed(&'a V),
+    Rotated { pivot: &'a V, direction: Side },
+}
+
+/// Only ever called through `&mut`, so it needs to be `Send` but not
+/// `Sync`: a shared reference to the tree gives no access to it.
+pub(crate) struct Observer<V>(Callback<V>);
+
+type Callback<V> = Box<dyn FnMut(Event<'_, V>) + Send>;
+
+// SAFETY: the closure is only reachable through `&mut Observer`, so sharing
+// `&Observer` between threads cannot call it concurrently.
+unsafe impl<V> Sync for Observer<V> {}
+
+/// An event noted while part of the tree was detached, delivered by
+/// `flush_events` once it is whole again.
+pub(crate) enum Pending<V> {
+    Inserted(NonNull<V>),
+    Rotated { pivot: NonNull<V>, direction: Side },
+}
+
+// SAFETY: pending events point into the tree that holds them and only exist
+// during an operation on it, so they behave like the `Box<V>`s they point
+// into.
+unsafe impl<V: Send> Send for Pending<V> {}
+unsafe impl<V: Sync> Sync for Pending<V> {}
+
+impl<V: Ord> AvlTree<V> {
+    pub fn set_observer<F>(&mut self, observer: F)
+    where
+        F: FnMut(Event<'_, V>) + Send + 'static,
+    {
+        self.extras_mut().observer = Some(Observer(Box::new(observer)));
+    }
+
+    pub fn clear_observer(&mut self) {
+        if let Some(ref mut extras) = self.extras {
+            extras.observer = None;
+        }
+    }
+
+    pub(crate) fn notify(&mut self, event: Event<'_, V>) {
+        if let Some(ref mut extras) = self.extras {
+            extras.notify(event);
+        }
+    }
+
+    /// Notes an event about a node that is about to be linked back into
+    /// the tree.
+    pub(crate) fn defer_inserted(&mut self, node: &AvlTreeNode<V>) {
+        if let Some(extras) = self.listening_extras() {
+            extras
+                .pending
+                .push(Pending::Inserted(NonNull::from(&node.val)));
+        }
+    }
+
+    /// Notes the rotations `node.rebalance()` is about to perform.
+    pub(crate) fn notify_rebalance(&mut self, node: &AvlTreeNode<V>) {
+        let Some(extras) = self.listening_extras() else {
+            return;
+        };
+
+        let mut rotated = |pivot: &V, direction| {
+            extras.pending.push(Pending::Rotated {
+                pivot: NonNull::from(pivot),
+                direction,
+            })
+        };
+
+        match node.get_balance() {
+            2.. => {
+                if let Some(ref left) = node.left {
+                    if left.get_balance() < 0 {
+                        rotated(&left.val, Side::Left);
+                    }
+                }
+                rotated(&node.val, Side::Right);
+            }
+            ..-1 => {
+                if let Some(ref right) = node.right {
+                    if right.get_balance() > 0 {
+                        rotated(&right.val, Side::Right);
+                    }
+                }
+                rotated(&node.val, Side::Left);
+            }
+            _ => (),
+        }
+    }
+
+    /// Delivers the events noted since the last flush. Must only be called
+    /// once every node is linked into the tree again.
+    pub(crate) fn flush_events(&mut self) {
+        let Some(ref mut extras) = self.extras else {
+            return;
+        };
+
+        for pending in mem::take(&mut extras.pending) {
+            // SAFETY: every pending event points at the value of a node in
+            // this tree. Rotations move nodes without freeing them, the node
+            // an operation removes is never one of them, and the tree is not
+            // modified again until the events are delivered.
+            let event = unsafe {
+                match pending {
+                    Pending::Inserted(value) => Event::Inserted(value.as_ref()),
+                    Pending::Rotated { pivot, direction } => Event::Rotated {
+                        pivot: pivot.as_ref(),
+                        direction,
+                    },
+                }
+            };
+            extras.notify(event);
+        }
+    }
+
+    /// Whether an observer or a recorder wants events.
+    pub(crate) fn is_observed(&self) -> bool {
+        self.extras
+            .as_ref()
+            .is_some_and(|extras| extras.observer.is_some() || extras.recorder.is_some())
+    }
+
+    fn listening_extras(&mut self) -> Option<&mut Extras<V>> {
+        if !self.is_observed() {
+            return None;
+        }
+        self.extras.as_deref_mut()
+    }
+}
+
+impl<V> Extras<V> {
+    fn notify(&mut self, event: Event<'_, V>) {
+        if let Some(ref mut recorder) = self.recorder {
+            recorder.record(&event);
+        }
+        if let Some(Observer(ref mut observer)) = self.observer {
+            observer(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::vec::Vec;
+    use core::cell::Cell;
+    use std::{
+        panic::{self, AssertUnwindSafe},
+        sync::{Arc, Mutex},
+    };
+
+    use crate::{AvlTree, Event, Side};
+
+    fn tree_of(values: impl IntoIterator<Item = u32>) -> AvlTree<u32> {
+        let mut tree = AvlTree::new();
+        for value in values {
+            tree.insert(value);
+        }
+        tree
+    }
+
+    #[test]
+    fn events_arrive_in_order_after_the_operation() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut tree = AvlTree::new();
+        let sink = Arc::clone(&events);
+        tree.set_observer(move |event: Event<'_, u32>| {
+            sink.lock().unwrap().push(match event {
+                Event::Inserted(&value) => (value, None),
+                Event::Removed(&value) => (value, Some(None)),
+                Event::Rotated { pivot, direction } => (*pivot, Some(Some(direction))),
+            })
+        });
+
+        tree.insert(1);
+        tree.insert(2);
+        tree.insert(3);
+        tree.remove(&1);
+        tree.remove(&2);
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                (1, None),
+                (2, None),
+                (3, None),
+                (1, Some(Some(Side::Left))),
+                (1, Some(None)),
+                (2, Some(None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn double_rotation_is_reported_child_first() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut tree = tree_of([3, 1]);
+        let sink = Arc::clone(&events);
+        tree.set_observer(move |event: Event<'_, u32>| {
+            if let Event::Rotated { pivot, direction } = event {
+                sink.lock().unwrap().push((*pivot, direction));
+            }
+        });
+
+        tree.insert(2);
+
+        assert_eq!(*events.lock().unwrap(), [(1, Side::Left), (3, Side::Right)]);
+    }
+
+    #[test]
+    fn panicking_observer_leaves_tree_intact() {
+        let mut tree = tree_of(0..1000);
+        tree.set_observer(|_| panic!("observer"));
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| tree.insert(1000)));
+        assert!(result.is_err());
+        assert_eq!(tree.len(), 1001);
+        assert_eq!(tree.check_invariants(), Ok(()));
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| tree.remove(&0)));
+        assert!(result.is_err());
+        assert_eq!(tree.len(), 1000);
+        assert_eq!(tree.check_invariants(), Ok(()));
+
+        tree.clear_observer();
+        tree.insert(0);
+        assert_eq!(tree.len(), 1001);
+    }
+
+    #[test]
+    fn observer_need_not_be_sync() {
+        fn assert_sync<T: Sync>(_: &T) {}
+
+        let count = Cell::new(0);
+        let mut tree = AvlTree::new();
+        tree.set_observer(move |_| count.set(count.get() + 1));
+        tree.insert(1);
+        assert_sync(&tree);
+    }
+}
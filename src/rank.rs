@@ -0,0 +1,313 @@
+use alloc::{collections::BTreeSet, vec::Vec};
+use core::{
+    borrow::Borrow,
+    cmp,
+    iter::FusedIterator,
+    ops::{Bound, RangeBounds},
+};
+
+use crate::{get_size, AvlTree};
+
+/// Every `step`-th value within a range, see [`AvlTree::range_step`].
+pub struct RangeStep<'a, V> {
+    tree: &'a AvlTree<V>,
+    next: usize,
+    end: usize,
+    step: usize,
+}
+
+impl<V: Ord> AvlTree<V> {
+    /// The number of values less than `value`, in O(log n).
+    pub fn rank<Q>(&self, value: &Q) -> usize
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.count_prefix(|val| val.borrow() < value)
+    }
+
+    /// The first, the `step + 1`-th and so on of the values within `range`,
+    /// selecting each by rank in O(log n) rather than walking past the
+    /// values in between. A `step` of 0 is taken as 1.
+    pub fn range_step<Q, R>(&self, range: R, step: usize) -> RangeStep<'_, V>
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let start = self.start_rank(range.start_bound());
+        let end = match range.end_bound() {
+            Bound::Included(end) => self.count_prefix(|val| val.borrow() <= end),
+            Bound::Excluded(end) => self.count_prefix(|val| val.borrow() < end),
+            Bound::Unbounded => self.len(),
+        };
+
+        RangeStep {
+            tree: self,
+            next: start,
+            end,
+            step: step.max(1),
+        }
+    }
+
+    /// The rank of the value [`AvlTree::partition_point`] finds, which is
+    /// `len()` if `pred` holds for every value, in O(log n).
+    pub fn partition_point_rank<F: FnMut(&V) -> bool>(&self, pred: F) -> usize {
+        self.count_prefix(pred)
+    }
+
+    /// The rank of the first value not below `start`.
+    pub(crate) fn start_rank<Q>(&self, start: Bound<&Q>) -> usize
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match start {
+            Bound::Included(start) => self.count_prefix(|val| val.borrow() < start),
+            Bound::Excluded(start) => self.count_prefix(|val| val.borrow() <= start),
+            Bound::Unbounded => 0,
+        }
+    }
+
+    /// The number of values `pred` holds for, given that it holds for a
+    /// prefix of the values in order.
+    fn count_prefix<F: FnMut(&V) -> bool>(&self, mut pred: F) -> usize {
+        let mut count = 0;
+        let mut cur = &self.root;
+
+        while let Some(ref node) = cur {
+            if pred(&node.val) {
+                count += get_size(&node.left) + 1;
+                cur = &node.right;
+            } else {
+                cur = &node.left;
+            }
+        }
+
+        count
+    }
+
+    /// The value with `rank` smaller values, in O(log n) using the subtree
+    /// sizes.
+    pub fn nth(&self, mut rank: usize) -> Option<&V> {
+        let mut cur = &self.root;
+
+        while let Some(ref node) = cur {
+            let left = get_size(&node.left);
+            match rank.cmp(&left) {
+                cmp::Ordering::Less => cur = &node.left,
+                cmp::Ordering::Equal => return Some(&node.val),
+                cmp::Ordering::Greater => {
+                    rank -= left + 1;
+                    cur = &node.right;
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Removes the value with `rank` smaller values, in O(log n). The path
+    /// is found from the subtree sizes before anything is taken apart.
+    pub(crate) fn take_nth(&mut self, mut rank: usize) -> Option<V> {
+        let mut steps = Vec::new();
+        let mut cur = &self.root;
+
+        while let Some(ref node) = cur {
+            let left = get_size(&node.left);
+            let step = rank.cmp(&left);
+            steps.push(step);
+            match step {
+                cmp::Ordering::Less => cur = &node.left,
+                cmp::Ordering::Equal => break,
+                cmp::Ordering::Greater => {
+                    rank -= left + 1;
+                    cur = &node.right;
+                }
+            }
+        }
+
+        let mut steps = steps.into_iter();
+        self.take_by(None, |_| steps.next())
+    }
+
+    /// A uniformly random value, in O(log n). `random_below(n)` must return
+    /// a uniformly random index less than `n`, e.g.
+    /// `|n| rng.gen_range(0..n)` with the `rand` crate.
+    pub fn choose<F: FnOnce(usize) -> usize>(&self, random_below: F) -> Option<&V> {
+        if self.is_empty() {
+            return None;
+        }
+        self.nth(random_below(self.len()))
+    }
+
+    /// `k` distinct values chosen uniformly at random, in ascending order,
+    /// or all values if there are fewer. Draws `k` indices with Floyd's
+    /// algorithm, calling `random_below` as for [`AvlTree::choose`], and
+    /// selects each in O(log n).
+    pub fn sample<F: FnMut(usize) -> usize>(&self, k: usize, mut random_below: F) -> Vec<&V> {
+        let len = self.len();
+        let k = cmp::min(k, len);
+
+        let mut ranks = BTreeSet::new();
+        for upper in len - k..len {
+            let rank = random_below(upper + 1);
+            if !ranks.insert(rank) {
+                ranks.insert(upper);
+            }
+        }
+
+        ranks
+            .into_iter()
+            .filter_map(|rank| self.nth(rank))
+            .collect()
+    }
+
+    /// The value with rank `k` among the values of both trees together,
+    /// counting a value present in both twice. Binary searches how many of
+    /// the smallest `k + 1` come from each tree, with O(log n) selections,
+    /// so O(log² n) in total.
+    pub fn kth_of_union<'a>(&'a self, other: &'a AvlTree<V>, k: usize) -> Option<&'a V> {
+        let (n, m) = (self.len(), other.len());
+        if k >= n + m {
+            return None;
+        }
+
+        // `i` values from `self` and `k + 1 - i` from `other`.
+        let (mut lo, mut hi) = ((k + 1).saturating_sub(m), cmp::min(k + 1, n));
+        loop {
+            let i = lo + (hi - lo) / 2;
+            let j = k + 1 - i;
+            let (last_a, next_a) = (i.checked_sub(1).and_then(|i| self.nth(i)), self.nth(i));
+            let (last_b, next_b) = (j.checked_sub(1).and_then(|j| other.nth(j)), other.nth(j));
+
+            if last_a.zip(next_b).is_some_and(|(a, b)| a > b) {
+                hi = i - 1;
+            } else if last_b.zip(next_a).is_some_and(|(b, a)| b > a) {
+                lo = i + 1;
+            } else {
+                return cmp::max(last_a, last_b);
+            }
+        }
+    }
+}
+
+impl<'a, V: Ord> Iterator for RangeStep<'a, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        if self.next >= self.end {
+            return None;
+        }
+        let value = self.tree.nth(self.next);
+        self.next = self.next.saturating_add(self.step);
+        value
+    }
+}
+
+impl<V: Ord> FusedIterator for RangeStep<'_, V> {}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::ops::Bound;
+
+    use crate::AvlTree;
+
+    fn tree_of(values: impl IntoIterator<Item = u32>) -> AvlTree<u32> {
+        let mut tree = AvlTree::new();
+        for value in values {
+            tree.insert(value);
+        }
+        tree
+    }
+
+    /// Checks every rank, and the first one past the end, against sorting
+    /// both trees together.
+    fn check_kth_of_union(a: &AvlTree<u32>, b: &AvlTree<u32>) {
+        let mut merged: Vec<u32> = a.iter().chain(b.iter()).copied().collect();
+        merged.sort();
+        for (k, expected) in merged.iter().enumerate() {
+            assert_eq!(a.kth_of_union(b, k), Some(expected), "k = {k}");
+            assert_eq!(b.kth_of_union(a, k), Some(expected), "k = {k}");
+        }
+        assert_eq!(a.kth_of_union(b, merged.len()), None);
+        assert_eq!(a.kth_of_union(b, usize::MAX), None);
+    }
+
+    #[test]
+    fn kth_of_union_matches_a_merge() {
+        let empty = AvlTree::new();
+        let evens = tree_of((0..40).map(|v| v * 2));
+        let thirds = tree_of((0..30).map(|v| v * 3));
+
+        check_kth_of_union(&empty, &empty);
+        check_kth_of_union(&evens, &empty);
+        check_kth_of_union(&evens, &thirds);
+        check_kth_of_union(&evens, &evens);
+        check_kth_of_union(&tree_of([5]), &tree_of([1, 9]));
+
+        assert_eq!(evens.kth_of_union(&thirds, 0), Some(&0));
+        assert_eq!(evens.kth_of_union(&thirds, 1), Some(&0));
+        assert_eq!(evens.kth_of_union(&thirds, 69), Some(&87));
+    }
+
+    #[test]
+    fn sample_returns_distinct_values_in_order() {
+        let tree = tree_of(0..100);
+
+        // Always drawing the largest index picks the top k ranks.
+        let sample = tree.sample(5, |n| n - 1);
+        assert_eq!(sample, [&95, &96, &97, &98, &99]);
+
+        // Always drawing 0 collides after the first draw, and Floyd's
+        // algorithm takes the upper bound of each draw instead.
+        let sample = tree.sample(4, |_| 0);
+        assert_eq!(sample, [&0, &97, &98, &99]);
+
+        let mut state = 12345u64;
+        let sample = tree.sample(30, |n| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (state >> 33) as usize % n
+        });
+        assert_eq!(sample.len(), 30);
+        assert!(sample.is_sorted_by(|a, b| a < b));
+
+        assert_eq!(tree.sample(200, |n| n - 1).len(), 100);
+        assert!(tree.sample(0, |_| unreachable!()).is_empty());
+        assert!(AvlTree::<u32>::new().sample(3, |_| 0).is_empty());
+    }
+
+    #[test]
+    fn range_step_selects_every_step_th_value() {
+        let tree = tree_of(0..20);
+        let stepped = |step| tree.range_step(3..=12, step).copied().collect::<Vec<_>>();
+
+        assert_eq!(stepped(0), (3..=12).collect::<Vec<_>>());
+        assert_eq!(stepped(1), (3..=12).collect::<Vec<_>>());
+        assert_eq!(stepped(4), [3, 7, 11]);
+        assert_eq!(stepped(10), [3]);
+        assert_eq!(stepped(usize::MAX), [3]);
+        assert_eq!(
+            tree.range_step(.., 7).copied().collect::<Vec<_>>(),
+            [0, 7, 14]
+        );
+        assert_eq!(tree.range_step(25.., 1).count(), 0);
+        let inverted = (Bound::Included(12), Bound::Excluded(3));
+        assert_eq!(tree.range_step(inverted, 1).count(), 0);
+    }
+
+    #[test]
+    fn partition_point_rank_matches_the_value() {
+        let mut tree = AvlTree::new();
+        for value in (0..50).map(|v| v * 3) {
+            tree.insert(value);
+        }
+
+        for split in [-1, 0, 1, 75, 146, 147, 200] {
+            let rank = tree.partition_point_rank(|&v| v < split);
+            assert_eq!(tree.nth(rank), tree.partition_point(|&v| v < split));
+            assert_eq!(rank, tree.iter().filter(|&&v| v < split).count());
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use alloc::vec::Vec;
+use core::ops::Bound;
+
+use crate::{Fingerprint, HashedAvlTree, SubtreeHash};
+
+/// Ranges holding at most this many values are sent in full rather than
+/// split further.
+const SEND_WHOLE: usize = 8;
+
+/// The bounds of a range being reconciled, usable as a `RangeBounds<V>`.
+pub type ReconcileRange<V> = (Bound<V>, Bound<V>);
+
+/// One message of the exchange between two [`HashedAvlTree`] replicas,
+/// see [`HashedAvlTree::reconcile_request`]. `H` is the hash type of the
+/// replicas' [`SubtreeHash`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReconcileMessage<V, H> {
+    /// The sender's fingerprint of the values within `range`.
+    Fingerprint {
+        range: ReconcileRange<V>,
+        fingerprint: Fingerprint<H>,
+    },
+    /// Every value the sender holds within `range`, in ascending order. When
+    /// `reply` is set, the receiver answers with the values it holds there
+    /// that are missing from `values`.
+    Values {
+        range: ReconcileRange<V>,
+        values: Vec<V>,
+        reply: bool,
+    },
+}
+
+impl<V: Ord + Clone, H: SubtreeHash<V>> HashedAvlTree<V, H> {
+    /// Opens a reconciliation with a remote replica, which brings both
+    /// replicas to the union of their values.
+    ///
+    /// Values are only ever added. A value one replica removed while the
+    /// other still holds it comes back, since nothing distinguishes a
+    /// removal from a value the other side has not seen yet. Replicas that
+    /// need removals to propagate must keep them as values of their own,
+    /// such as tombstones, and drop them once every replica has them.
+    ///
+    /// Each side passes the messages it receives to
+    /// [`reconcile_respond`](HashedAvlTree::reconcile_respond) and sends
+    /// back what that returns, until a side has nothing to send. Ranges
+    /// whose fingerprints agree are dropped, and differing ones are split
+    /// until they are small enough to send in full, so the traffic grows
+    /// with the number of differences rather than the size of the set.
+    pub fn reconcile_request(&self) -> Vec<ReconcileMessage<V, H::Hash>> {
+        let range = (Bound::Unbounded, Bound::Unbounded);
+        Vec::from([ReconcileMessage::Fingerprint {
+            fingerprint: self.fingerprint(range.clone()),
+            range,
+        }])
+    }
+
+    /// Answers the messages of a remote replica. Values received are
+    /// inserted into this tree. An empty answer ends the exchange.
+    pub fn reconcile_respond(
+        &mut self,
+        messages: &[ReconcileMessage<V, H::Hash>],
+    ) -> Vec<ReconcileMessage<V, H::Hash>> {
+        let mut answer = Vec::new();
+        for message in messages {
+            match message {
+                ReconcileMessage::Fingerprint { range, fingerprint } => {
+                    self.answer_fingerprint(range, *fingerprint, &mut answer)
+                }
+                ReconcileMessage::Values {
+                    range,
+                    values,
+                    reply,
+                } => {
+                    let missing = self.missing_from(range, values);
+                    self.extend(values.iter().cloned());
+                    if *reply && !missing.is_empty() {
+                        answer.push(ReconcileMessage::Values {
+                            range: range.clone(),
+                            values: missing,
+                            reply: false,
+                        });
+                    }
+                }
+            }
+        }
+        answer
+    }
+
+    fn answer_fingerprint(
+        &self,
+        range: &ReconcileRange<V>,
+        theirs: Fingerprint<H::Hash>,
+        answer: &mut Vec<ReconcileMessage<V, H::Hash>>,
+    ) {
+        let mine = self.fingerprint(range.clone());
+        if mine == theirs {
+            return;
+        }
+        if mine.len <= SEND_WHOLE {
+            answer.push(ReconcileMessage::Values {
+                range: range.clone(),
+                values: self.missing_from(range, &[]),
+                reply: true,
+            });
+            return;
+        }
+
+        // Split at our median, so each half holds at least one of our
+        // values and the ranges keep shrinking.
+        let before = match range.0 {
+            Bound::Included(ref start) => self.fingerprint(..start).len,
+            Bound::Excluded(ref start) => self.fingerprint(..=start).len,
+            Bound::Unbounded => 0,
+        };
+        let median = self
+            .nth(before + mine.len / 2)
+            .expect("the range holds more than half its length")
+            .clone();
+        for half in [
+            (range.0.clone(), Bound::Excluded(median.clone())),
+            (Bound::Included(median), range.1.clone()),
+        ] {
+            answer.push(ReconcileMessage::Fingerprint {
+                fingerprint: self.fingerprint(half.clone()),
+                range: half,
+            });
+        }
+    }
+
+    /// Our values within `range` that are not in the ascending `values`.
+    fn missing_from(&self, range: &ReconcileRange<V>, values: &[V]) -> Vec<V> {
+        let mut ours = Vec::new();
+        self.collect_range(range.0.as_ref(), range.1.as_ref(), &mut ours);
+        ours.into_iter()
+            .filter(|value| values.binary_search(value).is_err())
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::vec::Vec;
+    use core::hash::BuildHasherDefault;
+    use std::collections::hash_map::DefaultHasher;
+
+    use crate::{HashedAvlTree, Polynomial, ReconcileMessage};
+
+    type Tree = HashedAvlTree<u32, Polynomial<BuildHasherDefault<DefaultHasher>>>;
+
+    /// Runs the exchange to completion and returns how many values were
+    /// sent in `Values` messages.
+    fn reconcile(a: &mut Tree, b: &mut Tree) -> usize {
+        let mut sent = 0;
+        let mut messages = a.reconcile_request();
+        for turn in 0.. {
+            if messages.is_empty() {
+                break;
+            }
+            for message in &messages {
+                if let ReconcileMessage::Values { values, .. } = message {
+                    sent += values.len();
+                }
+            }
+            let to = if turn % 2 == 0 { &mut *b } else { &mut *a };
+            messages = to.reconcile_respond(&messages);
+        }
+        sent
+    }
+
+    #[test]
+    fn replicas_converge_to_the_union() {
+        let mut a = Tree::default();
+        let mut b = Tree::default();
+        a.extend(0..5000);
+        b.extend((0..5000).filter(|v| v % 997 != 3));
+        b.extend([7001, 7002, 9000]);
+        a.remove(&4000);
+
+        let sent = reconcile(&mut a, &mut b);
+        assert_eq!(a.root_hash(), b.root_hash());
+        assert!(a.iter().eq(b.iter()));
+        assert_eq!(a.len(), 5003);
+        assert!(sent < 200, "sent {sent} values");
+    }
+
+    #[test]
+    fn diverged_replicas_keep_values_removed_on_one_side() {
+        let mut a = Tree::default();
+        a.extend(0..1000);
+        let mut b = Tree::default();
+        b.extend(0..1000);
+
+        for value in (0..1000).step_by(50) {
+            a.remove(&value);
+        }
+        a.extend([2000, 2001]);
+        b.remove(&999);
+        b.extend([3000]);
+
+        reconcile(&mut a, &mut b);
+        let expected: Vec<u32> = (0..1000).chain([2000, 2001, 3000]).collect();
+        assert_eq!(a.iter().copied().collect::<Vec<_>>(), expected);
+        assert_eq!(b.iter().copied().collect::<Vec<_>>(), expected);
+        assert_eq!(a.root_hash(), b.root_hash());
+    }
+
+    #[test]
+    fn equal_and_empty_replicas() {
+        let mut a = Tree::default();
+        let mut b = Tree::default();
+        assert_eq!(reconcile(&mut a, &mut b), 0);
+
+        b.extend(0..100);
+        reconcile(&mut a, &mut b);
+        assert_eq!(
+            a.iter().copied().collect::<Vec<_>>(),
+            (0..100).collect::<Vec<_>>()
+        );
+
+        let request = a.reconcile_request();
+        assert!(b.reconcile_respond(&request).is_empty());
+    }
+}
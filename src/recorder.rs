@@ -0,0 +1,151 @@
+use alloc::vec::Vec;
+
+use crate::{AvlTree, Event, Side};
+
+/// One step captured by [`AvlTree::start_recording`].
+///
+/// Every insert or remove produces its `Inserted` or `Removed` frame, one
+/// `Rotated` frame per single rotation (in the order of [`Event::Rotated`])
+/// and finally a `Snapshot` of the settled tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame<V> {
+    Inserted(V),
+    Removed(V),
+    Rotated { pivot: V, direction: Side },
+    Snapshot(Vec<RecordedNode<V>>),
+}
+
+/// A node of a [`Frame::Snapshot`], listed in pre-order like
+/// [`AvlTree::structure`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedNode<V> {
+    pub value: V,
+    pub parent: Option<usize>,
+    pub side: Option<Side>,
+    pub height: usize,
+}
+
+pub(crate) struct Recorder<V> {
+    frames: Vec<Frame<V>>,
+    clone: fn(&V) -> V,
+}
+
+impl<V: Ord> AvlTree<V> {
+    /// Starts capturing frames, discarding any not yet taken.
+    pub fn start_recording(&mut self)
+    where
+        V: Clone,
+    {
+        self.extras_mut().recorder = Some(Recorder {
+            frames: Vec::new(),
+            clone: V::clone,
+        });
+    }
+
+    /// Stops capturing and returns the frames not yet taken.
+    pub fn stop_recording(&mut self) -> Vec<Frame<V>> {
+        self.extras
+            .as_mut()
+            .and_then(|extras| extras.recorder.take())
+            .map_or_else(Vec::new, |recorder| recorder.frames)
+    }
+
+    /// Returns the frames captured so far and keeps recording.
+    pub fn take_frames(&mut self) -> Vec<Frame<V>> {
+        self.recorder_mut()
+            .map_or_else(Vec::new, |recorder| core::mem::take(&mut recorder.frames))
+    }
+
+    pub(crate) fn record_snapshot(&mut self) {
+        let Some(clone) = self.recorder_mut().map(|recorder| recorder.clone) else {
+            return;
+        };
+
+        let snapshot = self
+            .structure()
+            .into_iter()
+            .map(|node| RecordedNode {
+                value: clone(node.value),
+                parent: node.parent,
+                side: node.side,
+                height: node.height,
+            })
+            .collect();
+
+        if let Some(recorder) = self.recorder_mut() {
+            recorder.frames.push(Frame::Snapshot(snapshot));
+        }
+    }
+
+    fn recorder_mut(&mut self) -> Option<&mut Recorder<V>> {
+        self.extras.as_mut()?.recorder.as_mut()
+    }
+}
+
+impl<V> Recorder<V> {
+    pub(crate) fn record(&mut self, event: &Event<'_, V>) {
+        let clone = self.clone;
+        self.frames.push(match *event {
+            Event::Inserted(value) => Frame::Inserted(clone(value)),
+            Event::Removed(value) => Frame::Removed(clone(value)),
+            Event::Rotated { pivot, direction } => Frame::Rotated {
+                pivot: clone(pivot),
+                direction,
+            },
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::{AvlTree, Frame, RecordedNode, Side};
+
+    fn node(
+        value: u32,
+        parent: Option<usize>,
+        side: Option<Side>,
+        height: usize,
+    ) -> RecordedNode<u32> {
+        RecordedNode {
+            value,
+            parent,
+            side,
+            height,
+        }
+    }
+
+    #[test]
+    fn frames_follow_each_operation() {
+        let mut tree = AvlTree::new();
+        tree.insert(1);
+        tree.start_recording();
+        tree.insert(2);
+        assert_eq!(tree.take_frames().len(), 2);
+        tree.insert(3);
+        tree.remove(&1);
+        let frames = tree.stop_recording();
+        tree.insert(4);
+
+        let root = node(2, None, None, 2);
+        assert_eq!(
+            frames,
+            [
+                Frame::Inserted(3),
+                Frame::Rotated {
+                    pivot: 1,
+                    direction: Side::Left,
+                },
+                Frame::Snapshot(Vec::from([
+                    root.clone(),
+                    node(1, Some(0), Some(Side::Left), 1),
+                    node(3, Some(0), Some(Side::Right), 1),
+                ])),
+                Frame::Removed(1),
+                Frame::Snapshot(Vec::from([root, node(3, Some(0), Some(Side::Right), 1)])),
+            ]
+        );
+        assert!(tree.take_frames().is_empty());
+    }
+}
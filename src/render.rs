@@ -0,0 +1,461 @@
+use alloc::string::String;
+use core::fmt::{self, Write};
+
+use crate::{AvlTree, AvlTreeNode, SubtreeView};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// Every level on one line, parents centered above their children.
+    /// This is what `Display` prints.
+    #[default]
+    Centered,
+    /// One node per line, indented below its parent with `├──` and `└──`
+    /// connectors. The left child is listed first; a missing child is shown
+    /// as `∅` when its sibling exists.
+    Tree,
+    /// The tree rotated a quarter turn: the root is in the first column and
+    /// every level is indented four more columns, with right subtrees above
+    /// their parent and left subtrees below.
+    Sideways,
+}
+
+/// Controls how [`AvlTree::display_with`] renders a tree.
+///
+/// Subtrees rooted at `max_depth` are replaced by the ellipsis, and values
+/// wider than `node_width` characters are cut short to end in the ellipsis.
+/// With `annotated` every value is followed by its node's height and
+/// balance factor (left height minus right height), as in `7(h=3,b=-1)`.
+/// In the centered layout `node_width` is also the width of every cell;
+/// without it cells are as wide as the widest rendered value.
+#[derive(Debug, Clone)]
+pub struct DisplayOptions {
+    layout: Layout,
+    max_depth: Option<usize>,
+    node_width: Option<usize>,
+    ellipsis: &'static str,
+    annotated: bool,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        DisplayOptions {
+            layout: Layout::default(),
+            max_depth: None,
+            node_width: None,
+            ellipsis: "…",
+            annotated: false,
+        }
+    }
+}
+
+impl DisplayOptions {
+    pub fn new() -> DisplayOptions {
+        DisplayOptions::default()
+    }
+
+    pub fn layout(mut self, layout: Layout) -> DisplayOptions {
+        self.layout = layout;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> DisplayOptions {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn node_width(mut self, node_width: usize) -> DisplayOptions {
+        self.node_width = Some(node_width);
+        self
+    }
+
+    pub fn ellipsis(mut self, ellipsis: &'static str) -> DisplayOptions {
+        self.ellipsis = ellipsis;
+        self
+    }
+
+    pub fn annotated(mut self, annotated: bool) -> DisplayOptions {
+        self.annotated = annotated;
+        self
+    }
+
+    fn is_elided(&self, depth: usize) -> bool {
+        self.max_depth.is_some_and(|max_depth| depth >= max_depth)
+    }
+}
+
+pub struct TreeDisplay<'a, V> {
+    root: Option<&'a AvlTreeNode<V>>,
+    options: DisplayOptions,
+}
+
+impl<V: Ord + fmt::Display> AvlTree<V> {
+    pub fn display_with(&self, options: DisplayOptions) -> TreeDisplay<'_, V> {
+        TreeDisplay {
+            root: self.root.as_deref(),
+            options,
+        }
+    }
+
+    pub fn tree_view(&self) -> TreeDisplay<'_, V> {
+        self.display_with(DisplayOptions::new().layout(Layout::Tree))
+    }
+
+    pub fn sideways(&self) -> TreeDisplay<'_, V> {
+        self.display_with(DisplayOptions::new().layout(Layout::Sideways))
+    }
+
+    pub fn annotated(&self) -> TreeDisplay<'_, V> {
+        self.display_with(DisplayOptions::new().annotated(true))
+    }
+}
+
+impl<V: Ord + fmt::Display> SubtreeView<'_, V> {
+    pub fn display_with(&self, options: DisplayOptions) -> TreeDisplay<'_, V> {
+        TreeDisplay {
+            root: Some(self.node),
+            options,
+        }
+    }
+}
+
+impl<V: fmt::Display> fmt::Display for SubtreeView<'_, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_tree(Some(self.node), &DisplayOptions::default(), f)
+    }
+}
+
+impl<V: fmt::Display> fmt::Display for TreeDisplay<'_, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_tree(self.root, &self.options, f)
+    }
+}
+
+pub(crate) fn write_tree<V: fmt::Display>(
+    root: Option<&AvlTreeNode<V>>,
+    options: &DisplayOptions,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    let Some(root) = root else {
+        return writeln!(f);
+    };
+
+    match options.layout {
+        Layout::Centered => write_centered(root, options, f),
+        Layout::Tree if options.is_elided(0) => writeln!(f, "{}", options.ellipsis),
+        Layout::Tree => {
+            write_label(&Label::new(root, options), options, f)?;
+            writeln!(f)?;
+            write_children(root, 1, &mut String::new(), options, f)
+        }
+        Layout::Sideways => write_sideways(root, 0, options, f),
+    }
+}
+
+fn write_centered<V: fmt::Display>(
+    root: &AvlTreeNode<V>,
+    options: &DisplayOptions,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    let height = root.height as usize;
+    let levels = options
+        .max_depth
+        .map_or(height, |max_depth| height.min(max_depth + 1));
+    let width = options
+        .node_width
+        .unwrap_or_else(|| widest_label(root, 0, options));
+
+    for depth in 0..levels {
+        let initial_space = (2_usize.pow((levels - depth - 1) as u32) - 1) * width;
+        write!(f, "{:initial_space$}", "")?;
+        let line = Line {
+            level: levels - depth,
+            width,
+            elided: options.is_elided(depth),
+        };
+        write_level(Some(root), depth, &line, options, f)?;
+        writeln!(f)?;
+    }
+    Ok(())
+}
+
+struct Line {
+    level: usize,
+    width: usize,
+    elided: bool,
+}
+
+fn write_level<V: fmt::Display>(
+    node: Option<&AvlTreeNode<V>>,
+    descend_by: usize,
+    line: &Line,
+    options: &DisplayOptions,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    let width = line.width;
+    if descend_by > 0 {
+        let space_between_nodes = (2_usize.pow(line.level as u32) - 1) * width;
+        let (left, right) = match node {
+            None => (None, None),
+            Some(node) => (node.left.as_deref(), node.right.as_deref()),
+        };
+        write_level(left, descend_by - 1, line, options, f)?;
+        write!(f, "{:space_between_nodes$}", "")?;
+        return write_level(right, descend_by - 1, line, options, f);
+    }
+
+    match node {
+        None => write!(f, "{:width$}", ""),
+        Some(_) if line.elided => {
+            let padding = width.saturating_sub(display_width(options.ellipsis));
+            let (before, after) = (padding / 2, padding - padding / 2);
+            write!(f, "{:before$}{}{:after$}", "", options.ellipsis, "")
+        }
+        Some(node) => {
+            let padding = width.saturating_sub(label_width(&Label::new(node, options), options));
+            let (before, after) = (padding / 2, padding - padding / 2);
+            write!(f, "{:before$}", "")?;
+            write_label(&Label::new(node, options), options, f)?;
+            write!(f, "{:after$}", "")
+        }
+    }
+}
+
+fn widest_label<V: fmt::Display>(
+    node: &AvlTreeNode<V>,
+    depth: usize,
+    options: &DisplayOptions,
+) -> usize {
+    if options.is_elided(depth) {
+        return display_width(options.ellipsis);
+    }
+
+    let mut widest = label_width(&Label::new(node, options), options);
+    for child in [&node.left, &node.right].into_iter().flatten() {
+        widest = widest.max(widest_label(child, depth + 1, options));
+    }
+    widest
+}
+
+fn write_children<V: fmt::Display>(
+    node: &AvlTreeNode<V>,
+    depth: usize,
+    prefix: &mut String,
+    options: &DisplayOptions,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    if node.left.is_none() && node.right.is_none() {
+        return Ok(());
+    }
+
+    for (child, branch, indent) in [(&node.left, "├── ", "│   "), (&node.right, "└── ", "    ")]
+    {
+        match child {
+            None => writeln!(f, "{}{}∅", prefix, branch)?,
+            Some(_) if options.is_elided(depth) => {
+                writeln!(f, "{}{}{}", prefix, branch, options.ellipsis)?
+            }
+            Some(child) => {
+                write!(f, "{}{}", prefix, branch)?;
+                write_label(&Label::new(child, options), options, f)?;
+                writeln!(f)?;
+                let len = prefix.len();
+                prefix.push_str(indent);
+                write_children(child, depth + 1, prefix, options, f)?;
+                prefix.truncate(len);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_sideways<V: fmt::Display>(
+    node: &AvlTreeNode<V>,
+    depth: usize,
+    options: &DisplayOptions,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    let indent = depth * 4;
+    if options.is_elided(depth) {
+        return writeln!(f, "{:indent$}{}", "", options.ellipsis);
+    }
+
+    if let Some(ref right) = node.right {
+        write_sideways(right, depth + 1, options, f)?;
+    }
+    write!(f, "{:indent$}", "")?;
+    write_label(&Label::new(node, options), options, f)?;
+    writeln!(f)?;
+    if let Some(ref left) = node.left {
+        write_sideways(left, depth + 1, options, f)?;
+    }
+    Ok(())
+}
+
+struct Label<'a, V> {
+    node: &'a AvlTreeNode<V>,
+    annotated: bool,
+}
+
+impl<'a, V> Label<'a, V> {
+    fn new(node: &'a AvlTreeNode<V>, options: &DisplayOptions) -> Label<'a, V> {
+        Label {
+            node,
+            annotated: options.annotated,
+        }
+    }
+}
+
+impl<V: fmt::Display> fmt::Display for Label<'_, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.node.val)?;
+        if self.annotated {
+            write!(f, "(h={},b={})", self.node.height, self.node.get_balance())?;
+        }
+        Ok(())
+    }
+}
+
+fn label_width<T: fmt::Display + ?Sized>(label: &T, options: &DisplayOptions) -> usize {
+    let width = display_width(label);
+    match options.node_width {
+        Some(limit) if width > limit => limit.max(display_width(options.ellipsis)),
+        _ => width,
+    }
+}
+
+fn write_label<T: fmt::Display + ?Sized>(
+    label: &T,
+    options: &DisplayOptions,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    match options.node_width {
+        Some(limit) if display_width(label) > limit => {
+            let mut truncated = Truncated {
+                f,
+                remaining: limit.saturating_sub(display_width(options.ellipsis)),
+            };
+            write!(truncated, "{}", label)?;
+            truncated.f.write_str(options.ellipsis)
+        }
+        _ => write!(f, "{}", label),
+    }
+}
+
+pub(crate) fn display_width<T: fmt::Display + ?Sized>(value: &T) -> usize {
+    struct CharCounter(usize);
+
+    impl Write for CharCounter {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.0 += s.chars().count();
+            Ok(())
+        }
+    }
+
+    let mut counter = CharCounter(0);
+    let _ = write!(counter, "{}", value);
+    counter.0
+}
+
+/// Passes through at most `remaining` characters.
+struct Truncated<'a, 'b> {
+    f: &'a mut fmt::Formatter<'b>,
+    remaining: usize,
+}
+
+impl Write for Truncated<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars().take(self.remaining) {
+            self.f.write_char(c)?;
+            self.remaining -= 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, string::String};
+
+    use crate::{AvlTree, DisplayOptions, Layout};
+
+    /// 4 with children 2 and 6, where 6 has only a left child.
+    fn sample() -> AvlTree<u32> {
+        let mut tree = AvlTree::new();
+        for value in [4, 2, 6, 1, 3, 5] {
+            tree.insert(value);
+        }
+        tree
+    }
+
+    fn lines(lines: &[&str]) -> String {
+        lines.iter().map(|line| format!("{line}\n")).collect()
+    }
+
+    #[test]
+    fn centered_layout() {
+        let tree = sample();
+        assert_eq!(format!("{tree}"), lines(&["   4", " 2   6", "1 3 5  "]));
+        assert_eq!(
+            format!("{}", tree.display_with(DisplayOptions::new().max_depth(1))),
+            lines(&[" 4", "… …"])
+        );
+        assert_eq!(
+            format!("{}", tree.annotated()),
+            lines(&[
+                "                              4(h=3,b=0)",
+                "          2(h=2,b=0)                              6(h=2,b=1)",
+                "1(h=1,b=0)          3(h=1,b=0)          5(h=1,b=0)                    ",
+            ])
+        );
+    }
+
+    #[test]
+    fn tree_layout() {
+        assert_eq!(
+            format!("{}", sample().tree_view()),
+            lines(&[
+                "4",
+                "├── 2",
+                "│   ├── 1",
+                "│   └── 3",
+                "└── 6",
+                "    ├── 5",
+                "    └── ∅",
+            ])
+        );
+    }
+
+    #[test]
+    fn sideways_layout() {
+        assert_eq!(
+            format!("{}", sample().sideways()),
+            lines(&["    6", "        5", "4", "        3", "    2", "        1"])
+        );
+    }
+
+    #[test]
+    fn long_and_non_ascii_labels_are_cut_by_chars() {
+        let mut tree = AvlTree::new();
+        for value in ["mango", "kiwi", "pomegranate", "äpfelchen"] {
+            tree.insert(String::from(value));
+        }
+
+        assert_eq!(
+            format!("{}", tree.display_with(DisplayOptions::new().node_width(4))),
+            lines(&[
+                "            man…",
+                "    kiwi            pom…",
+                "                        äpf…",
+            ])
+        );
+        let options = DisplayOptions::new()
+            .node_width(4)
+            .ellipsis("..")
+            .layout(Layout::Tree);
+        assert_eq!(
+            format!("{}", tree.display_with(options)),
+            lines(&["ma..", "├── kiwi", "└── po..", "    ├── ∅", "    └── äp.."])
+        );
+    }
+}
@@ -0,0 +1,351 @@
+use std::sync::{PoisonError, RwLock};
+
+use alloc::vec::{self, Vec};
+use core::{borrow::Borrow, iter::FusedIterator, ops::Bound};
+
+use crate::AvlTree;
+
+const DEFAULT_MAX_SHARD_LEN: usize = 1 << 16;
+
+/// A set split by value range over several trees, each behind its own
+/// lock, so writes to different ranges proceed in parallel.
+///
+/// Operations take `&self` and lock only the shard they touch. A shard
+/// that grows past the maximum length is split at its median, which
+/// briefly locks the whole set.
+pub struct ShardedAvlTree<V> {
+    shards: RwLock<Vec<Shard<V>>>,
+    max_shard_len: usize,
+}
+
+struct Shard<V> {
+    /// The smallest value the shard may hold, `None` for the first shard.
+    lower: Option<V>,
+    tree: RwLock<AvlTree<V>>,
+}
+
+/// Iterator over clones of the values of a [`ShardedAvlTree`] in
+/// ascending order, see [`ShardedAvlTree::iter`].
+pub struct ShardedIter<'a, V> {
+    set: &'a ShardedAvlTree<V>,
+    /// The last value taken into `buffer`, which the next shard's values
+    /// must exceed.
+    after: Option<V>,
+    buffer: vec::IntoIter<V>,
+    done: bool,
+}
+
+impl<V: Ord + Clone> Default for ShardedAvlTree<V> {
+    fn default() -> Self {
+        ShardedAvlTree::with_max_shard_len(DEFAULT_MAX_SHARD_LEN)
+    }
+}
+
+impl<V: Ord + Clone> ShardedAvlTree<V> {
+    pub fn new() -> ShardedAvlTree<V> {
+        ShardedAvlTree::default()
+    }
+
+    pub fn with_max_shard_len(max_shard_len: usize) -> ShardedAvlTree<V> {
+        ShardedAvlTree {
+            shards: RwLock::new(alloc::vec![Shard {
+                lower: None,
+                tree: RwLock::new(AvlTree::new()),
+            }]),
+            max_shard_len: max_shard_len.max(2),
+        }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    pub fn len(&self) -> usize {
+        let shards = self.shards.read().unwrap_or_else(PoisonError::into_inner);
+        shards.iter().map(|shard| read(&shard.tree).len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn insert(&self, value: V) {
+        let overfull = {
+            let shards = self.shards.read().unwrap_or_else(PoisonError::into_inner);
+            let index = shard_index(&shards, &value);
+            let mut tree = write(&shards[index].tree);
+            tree.insert(value);
+            tree.len() > self.max_shard_len
+        };
+
+        if overfull {
+            self.split_overfull();
+        }
+    }
+
+    pub fn remove<Q>(&self, value: &Q) -> bool
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let shards = self.shards.read().unwrap_or_else(PoisonError::into_inner);
+        let index = shard_index(&shards, value);
+        let removed = write(&shards[index].tree).remove(value);
+        removed
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let shards = self.shards.read().unwrap_or_else(PoisonError::into_inner);
+        let index = shard_index(&shards, value);
+        let contains = read(&shards[index].tree).contains(value);
+        contains
+    }
+
+    /// Calls `f` with every value in ascending order, holding one shard's
+    /// read lock at a time.
+    pub fn for_each<F: FnMut(&V)>(&self, mut f: F) {
+        let shards = self.shards.read().unwrap_or_else(PoisonError::into_inner);
+        for shard in shards.iter() {
+            read(&shard.tree).iter().for_each(&mut f);
+        }
+    }
+
+    /// Clones of every value in ascending order. The values of one shard are
+    /// copied out under its read lock, and no lock is held between shards,
+    /// so writers are never blocked for long and the iterating thread may
+    /// write to the set itself. Values written meanwhile may or may not be
+    /// seen, but the values returned always ascend strictly.
+    pub fn iter(&self) -> ShardedIter<'_, V> {
+        ShardedIter {
+            set: self,
+            after: None,
+            buffer: Vec::new().into_iter(),
+            done: false,
+        }
+    }
+
+    /// Splits every shard above the maximum length at its median. Other
+    /// threads may have split it already by the time the lock is taken.
+    fn split_overfull(&self) {
+        let mut shards = self.shards.write().unwrap_or_else(PoisonError::into_inner);
+
+        let mut index = 0;
+        while index < shards.len() {
+            let tree = shards[index]
+                .tree
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner);
+            if tree.len() <= self.max_shard_len {
+                index += 1;
+                continue;
+            }
+
+            let Some(median) = tree.nth(tree.len() / 2).cloned() else {
+                index += 1;
+                continue;
+            };
+            let (less, equal, mut greater) = core::mem::take(tree).split3(&median);
+            if let Some(equal) = equal {
+                greater.insert(equal);
+            }
+            *tree = less;
+            shards.insert(
+                index + 1,
+                Shard {
+                    lower: Some(median),
+                    tree: RwLock::new(greater),
+                },
+            );
+        }
+    }
+}
+
+impl<'a, V: Ord + Clone> IntoIterator for &'a ShardedAvlTree<V> {
+    type Item = V;
+    type IntoIter = ShardedIter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<V: Ord + Clone> ShardedIter<'_, V> {
+    /// Copies the values above `after` from the first shard that has any.
+    /// Shards may have been split since the last call, so the shard is
+    /// looked up by value rather than by position.
+    fn refill(&mut self) {
+        let shards = self
+            .set
+            .shards
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut index = self
+            .after
+            .as_ref()
+            .map_or(0, |after| shard_index(&shards, after));
+
+        while let Some(shard) = shards.get(index) {
+            let tree = read(&shard.tree);
+            let values: Vec<V> = match self.after {
+                Some(ref after) => tree
+                    .range::<V, _>((Bound::Excluded(after), Bound::Unbounded))
+                    .cloned()
+                    .collect(),
+                None => tree.iter().cloned().collect(),
+            };
+            if let Some(last) = values.last() {
+                self.after = Some(last.clone());
+                self.buffer = values.into_iter();
+                return;
+            }
+            index += 1;
+        }
+        self.done = true;
+    }
+}
+
+impl<V: Ord + Clone> Iterator for ShardedIter<'_, V> {
+    type Item = V;
+
+    fn next(&mut self) -> Option<V> {
+        loop {
+            if let Some(value) = self.buffer.next() {
+                return Some(value);
+            }
+            if self.done {
+                return None;
+            }
+            self.refill();
+        }
+    }
+}
+
+impl<V: Ord + Clone> FusedIterator for ShardedIter<'_, V> {}
+
+/// The last shard whose lower bound is not above `value`.
+fn shard_index<V, Q>(shards: &[Shard<V>], value: &Q) -> usize
+where
+    V: Borrow<Q>,
+    Q: Ord + ?Sized,
+{
+    shards
+        .partition_point(|shard| {
+            shard
+                .lower
+                .as_ref()
+                .is_none_or(|lower| lower.borrow() <= value)
+        })
+        .saturating_sub(1)
+}
+
+fn read<V>(lock: &RwLock<AvlTree<V>>) -> std::sync::RwLockReadGuard<'_, AvlTree<V>> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write<V>(lock: &RwLock<AvlTree<V>>) -> std::sync::RwLockWriteGuard<'_, AvlTree<V>> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use alloc::vec::Vec;
+
+    use crate::ShardedAvlTree;
+
+    fn lowers(set: &ShardedAvlTree<u32>) -> Vec<Option<u32>> {
+        let shards = set.shards.read().unwrap();
+        shards.iter().map(|shard| shard.lower).collect()
+    }
+
+    #[test]
+    fn overfull_shards_split_at_their_median() {
+        let set = ShardedAvlTree::with_max_shard_len(4);
+        for value in [10, 20, 30, 40] {
+            set.insert(value);
+        }
+        assert_eq!(set.shard_count(), 1);
+
+        set.insert(50);
+        assert_eq!(lowers(&set), [None, Some(30)]);
+        set.insert(25);
+        set.insert(35);
+        assert_eq!(lowers(&set), [None, Some(30)]);
+        set.insert(45);
+        assert_eq!(lowers(&set), [None, Some(30), Some(40)]);
+        set.insert(55);
+
+        assert!(set.remove(&30));
+        assert!(!set.contains(&30));
+        set.insert(30);
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            [10, 20, 25, 30, 35, 40, 45, 50, 55]
+        );
+        assert_eq!(set.len(), 9);
+    }
+
+    #[test]
+    fn iteration_ascends_across_shards_while_writing() {
+        let set = ShardedAvlTree::with_max_shard_len(8);
+        for value in (0..100).rev() {
+            set.insert(value * 2);
+        }
+
+        // Odd values inserted during the iteration split shards behind and
+        // ahead of it.
+        let mut seen = Vec::new();
+        for value in &set {
+            if value % 20 == 0 {
+                set.insert(value + 1);
+                set.insert(199 - value);
+            }
+            seen.push(value);
+        }
+        assert!(seen.is_sorted_by(|a, b| a < b));
+        assert!((0..100).all(|value| seen.contains(&(value * 2))));
+    }
+
+    #[test]
+    fn concurrent_inserts_from_several_threads() {
+        let set = ShardedAvlTree::with_max_shard_len(64);
+        thread::scope(|scope| {
+            for thread in 0..4 {
+                let set = &set;
+                scope.spawn(move || {
+                    for value in (0..2000).filter(|value| value % 4 == thread) {
+                        set.insert(value);
+                    }
+                    for value in (0..2000).filter(|value| value % 40 == thread) {
+                        assert!(set.remove(&value));
+                    }
+                });
+            }
+        });
+
+        let expected: Vec<u32> = (0..2000).filter(|value| value % 40 >= 4).collect();
+        assert_eq!(set.len(), expected.len());
+        assert_eq!(set.iter().collect::<Vec<_>>(), expected);
+
+        let shards = set.shards.read().unwrap();
+        assert!(shards.len() > 1);
+        for (index, shard) in shards.iter().enumerate() {
+            let tree = shard.tree.read().unwrap();
+            assert!(tree.len() <= 64);
+            if let (Some(lower), Some(min)) = (shard.lower, tree.min()) {
+                assert!(lower <= *min);
+            }
+            if let Some(next) = shards.get(index + 1) {
+                assert!(tree.max().is_none_or(|max| Some(*max) < next.lower));
+            }
+        }
+    }
+}
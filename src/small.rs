@@ -0,0 +1,335 @@
+use alloc::vec::Vec;
+use core::{iter::FusedIterator, mem, mem::MaybeUninit, ptr, slice};
+
+use crate::{AvlTree, Iter};
+
+/// A set that keeps up to `N` values sorted in place and only moves them
+/// into an [`AvlTree`] once it outgrows that. `N` is at most 255.
+pub struct SmallAvlTree<V, const N: usize = 8> {
+    repr: Repr<V, N>,
+}
+
+enum Repr<V, const N: usize> {
+    // The first `len` items are initialized and strictly ascending.
+    Inline { len: u8, items: [MaybeUninit<V>; N] },
+    Tree(AvlTree<V>),
+}
+
+/// In-order iterator over a [`SmallAvlTree`], see [`SmallAvlTree::iter`].
+pub struct SmallIter<'a, V> {
+    inner: SmallIterInner<'a, V>,
+}
+
+enum SmallIterInner<'a, V> {
+    Inline(slice::Iter<'a, V>),
+    Tree(Iter<'a, V>),
+}
+
+impl<V, const N: usize> Default for SmallAvlTree<V, N> {
+    fn default() -> Self {
+        const {
+            assert!(
+                N <= u8::MAX as usize,
+                "SmallAvlTree holds at most 255 values inline"
+            )
+        };
+
+        SmallAvlTree {
+            repr: Repr::Inline {
+                len: 0,
+                items: [const { MaybeUninit::uninit() }; N],
+            },
+        }
+    }
+}
+
+impl<V, const N: usize> Drop for SmallAvlTree<V, N> {
+    fn drop(&mut self) {
+        if let Repr::Inline { len, ref mut items } = self.repr {
+            // SAFETY: the first `len` items are initialized and dropped
+            // only here.
+            unsafe { ptr::drop_in_place(initialized_mut(items, len)) }
+        }
+    }
+}
+
+impl<V: Ord, const N: usize> SmallAvlTree<V, N> {
+    pub fn new() -> SmallAvlTree<V, N> {
+        SmallAvlTree::default()
+    }
+
+    pub fn is_inline(&self) -> bool {
+        matches!(self.repr, Repr::Inline { .. })
+    }
+
+    pub fn len(&self) -> usize {
+        match self.repr {
+            Repr::Inline { len, .. } => len as usize,
+            Repr::Tree(ref tree) => tree.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> SmallIter<'_, V> {
+        SmallIter {
+            inner: match self.repr {
+                Repr::Inline { len, ref items } => {
+                    SmallIterInner::Inline(initialized(items, len).iter())
+                }
+                Repr::Tree(ref tree) => SmallIterInner::Tree(tree.iter()),
+            },
+        }
+    }
+
+    pub fn min(&self) -> Option<&V> {
+        match self.repr {
+            Repr::Inline { len, ref items } => initialized(items, len).first(),
+            Repr::Tree(ref tree) => tree.min(),
+        }
+    }
+
+    pub fn max(&self) -> Option<&V> {
+        match self.repr {
+            Repr::Inline { len, ref items } => initialized(items, len).last(),
+            Repr::Tree(ref tree) => tree.max(),
+        }
+    }
+
+    pub fn insert(&mut self, value: V) {
+        match self.repr {
+            Repr::Inline {
+                ref mut len,
+                ref mut items,
+            } => {
+                let pos = match initialized(items, *len).binary_search(&value) {
+                    Ok(_) => return,
+                    Err(pos) => pos,
+                };
+
+                let count = *len as usize;
+                if count < N {
+                    // SAFETY: `count < N`, so shifting the items from `pos`
+                    // up by one stays in bounds, and the gap at `pos` is
+                    // filled before `len` counts it.
+                    unsafe {
+                        let base = items.as_mut_ptr();
+                        ptr::copy(base.add(pos), base.add(pos + 1), count - pos);
+                        base.add(pos).write(MaybeUninit::new(value));
+                    }
+                    *len += 1;
+                    return;
+                }
+
+                let mut values = Vec::with_capacity(count + 1);
+                *len = 0;
+                // SAFETY: the `count` initialized items are moved out once,
+                // and `len` no longer counts them.
+                values.extend(
+                    items[..count]
+                        .iter()
+                        .map(|item| unsafe { item.assume_init_read() }),
+                );
+                values.insert(pos, value);
+                let tree = AvlTree::from_sorted(&mut values.into_iter(), count + 1);
+                self.repr = Repr::Tree(tree);
+            }
+            Repr::Tree(ref mut tree) => {
+                tree.insert(value);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, value: &V) -> bool {
+        match self.repr {
+            Repr::Inline {
+                ref mut len,
+                ref mut items,
+            } => match initialized(items, *len).binary_search(value) {
+                Ok(pos) => {
+                    let count = *len as usize;
+                    // SAFETY: `pos < count`, so the item at `pos` is
+                    // initialized. It is moved out and the items above it
+                    // shifted down before `len` stops counting the last slot.
+                    let removed = unsafe {
+                        let base = items.as_mut_ptr();
+                        let removed = base.add(pos).read().assume_init();
+                        ptr::copy(base.add(pos + 1), base.add(pos), count - pos - 1);
+                        removed
+                    };
+                    *len -= 1;
+                    drop(removed);
+                    true
+                }
+                Err(_) => false,
+            },
+            Repr::Tree(ref mut tree) => tree.remove(value),
+        }
+    }
+
+    pub fn contains(&self, value: &V) -> bool {
+        match self.repr {
+            Repr::Inline { len, ref items } => initialized(items, len).binary_search(value).is_ok(),
+            Repr::Tree(ref tree) => tree.contains(value),
+        }
+    }
+}
+
+fn initialized<V>(items: &[MaybeUninit<V>], len: u8) -> &[V] {
+    // SAFETY: callers pass the `len` of an inline representation, whose
+    // first `len` items are initialized.
+    unsafe { slice::from_raw_parts(items.as_ptr().cast::<V>(), len as usize) }
+}
+
+fn initialized_mut<V>(items: &mut [MaybeUninit<V>], len: u8) -> &mut [V] {
+    // SAFETY: as for `initialized`.
+    unsafe { slice::from_raw_parts_mut(items.as_mut_ptr().cast::<V>(), len as usize) }
+}
+
+impl<V: Ord, const N: usize> IntoIterator for SmallAvlTree<V, N> {
+    type Item = V;
+    type IntoIter = <Vec<V> as IntoIterator>::IntoIter;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        match mem::replace(&mut self.repr, Repr::Tree(AvlTree::new())) {
+            Repr::Inline { len, items } => {
+                // SAFETY: the first `len` items are initialized, and the
+                // array was moved out of `self`, so nothing else drops them.
+                initialized(&items, len)
+                    .iter()
+                    .map(|item| unsafe { ptr::read(item) })
+                    .collect::<Vec<_>>()
+                    .into_iter()
+            }
+            Repr::Tree(tree) => tree.into_iter(),
+        }
+    }
+}
+
+impl<'a, V: Ord, const N: usize> IntoIterator for &'a SmallAvlTree<V, N> {
+    type Item = &'a V;
+    type IntoIter = SmallIter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, V> Iterator for SmallIter<'a, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        match self.inner {
+            SmallIterInner::Inline(ref mut iter) => iter.next(),
+            SmallIterInner::Tree(ref mut iter) => iter.next(),
+        }
+    }
+}
+
+impl<V> FusedIterator for SmallIter<'_, V> {}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::{rc::Rc, vec::Vec};
+    use core::cell::Cell;
+
+    use crate::SmallAvlTree;
+
+    #[test]
+    #[cfg(not(feature = "metrics"))]
+    fn inline_storage_is_compact() {
+        assert_eq!(core::mem::size_of::<SmallAvlTree<u32, 8>>(), 40);
+    }
+
+    #[test]
+    fn stays_sorted_inline_and_after_spilling() {
+        let mut small: SmallAvlTree<u32, 4> = SmallAvlTree::new();
+        for value in [3, 1, 4, 1, 2] {
+            small.insert(value);
+        }
+        assert!(small.is_inline());
+        assert_eq!(small.len(), 4);
+        assert_eq!(small.iter().copied().collect::<Vec<_>>(), [1, 2, 3, 4]);
+
+        assert!(small.remove(&2));
+        assert!(!small.remove(&2));
+        assert_eq!((small.min(), small.max()), (Some(&1), Some(&4)));
+
+        small.insert(0);
+        small.insert(5);
+        assert!(!small.is_inline());
+        assert_eq!(small.len(), 5);
+        assert_eq!(small.into_iter().collect::<Vec<_>>(), [0, 1, 3, 4, 5]);
+    }
+
+    /// Counts its own drops and orders by `key`.
+    #[derive(Debug)]
+    struct Counted {
+        key: u32,
+        drops: Rc<Cell<usize>>,
+    }
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+        }
+    }
+
+    impl PartialEq for Counted {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+
+    impl Eq for Counted {}
+
+    impl PartialOrd for Counted {
+        fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Counted {
+        fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+            self.key.cmp(&other.key)
+        }
+    }
+
+    #[test]
+    fn every_value_is_dropped_exactly_once() {
+        let drops = Rc::new(Cell::new(0));
+        let counted = |key| Counted {
+            key,
+            drops: Rc::clone(&drops),
+        };
+
+        let mut small: SmallAvlTree<Counted, 4> = SmallAvlTree::new();
+        for key in [2, 1, 3, 1] {
+            small.insert(counted(key));
+        }
+        assert_eq!(drops.get(), 1);
+        small.remove(&counted(2));
+        assert_eq!(drops.get(), 3);
+        drop(small);
+        assert_eq!(drops.get(), 5);
+
+        let mut small: SmallAvlTree<Counted, 2> = SmallAvlTree::new();
+        for key in 0..3 {
+            small.insert(counted(key));
+        }
+        assert!(!small.is_inline());
+        drop(small);
+        assert_eq!(drops.get(), 8);
+
+        let mut small: SmallAvlTree<Counted, 4> = SmallAvlTree::new();
+        small.insert(counted(7));
+        let values: Vec<_> = small.into_iter().collect();
+        assert_eq!(drops.get(), 8);
+        drop(values);
+        assert_eq!(drops.get(), 9);
+    }
+}
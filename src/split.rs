@@ -0,0 +1,283 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    borrow::Borrow,
+    cmp,
+    ops::{Bound, RangeBounds},
+};
+
+use crate::{get_height, AvlTree, AvlTreeNode, DuplicatePolicy, Event, Link, SelfCheck};
+
+impl<V: Ord> AvlTree<V> {
+    /// Splits the tree into the values less than `value`, the value equal to
+    /// it if present, and the values greater than it, in O(log n).
+    pub fn split3<Q>(mut self, value: &Q) -> (AvlTree<V>, Option<V>, AvlTree<V>)
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (less, equal, greater) = split(self.root.take(), value);
+        let (mut less, mut greater) = (AvlTree::from_link(less), AvlTree::from_link(greater));
+        less.set_max_imbalance(self.max_imbalance());
+        greater.set_max_imbalance(self.max_imbalance());
+        (less, equal, greater)
+    }
+
+    /// Drops every value outside `range` by splitting at its bounds, in
+    /// O(log n) plus the number of values dropped. An observer or recorder
+    /// gets one `Removed` event per dropped value in ascending order,
+    /// followed by a single snapshot.
+    pub fn retain_range<Q, R>(&mut self, range: R)
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let observed = self.is_observed();
+        let mut removed = Vec::new();
+        let mut discard = |link: Link<V>, value: Option<V>| {
+            if observed {
+                removed.extend(AvlTree::from_link(link));
+                removed.extend(value);
+            }
+        };
+        let mut root = self.root.take();
+
+        root = match range.start_bound() {
+            Bound::Included(start) => {
+                let (less, equal, greater) = split(root, start);
+                discard(less, None);
+                match equal {
+                    Some(value) => Some(join(None, self.alloc_node(value), greater)),
+                    None => greater,
+                }
+            }
+            Bound::Excluded(start) => {
+                let (less, equal, greater) = split(root, start);
+                discard(less, equal);
+                greater
+            }
+            Bound::Unbounded => root,
+        };
+        root = match range.end_bound() {
+            Bound::Included(end) => {
+                let (less, equal, greater) = split(root, end);
+                discard(greater, None);
+                match equal {
+                    Some(value) => Some(join(less, self.alloc_node(value), None)),
+                    None => less,
+                }
+            }
+            Bound::Excluded(end) => {
+                let (less, equal, greater) = split(root, end);
+                discard(None, equal);
+                discard(greater, None);
+                less
+            }
+            Bound::Unbounded => root,
+        };
+
+        self.root = root;
+        if !removed.is_empty() {
+            for value in &removed {
+                self.notify(Event::Removed(value));
+            }
+            self.record_snapshot();
+        }
+        if self.self_check_level() == SelfCheck::Full {
+            self.run_self_check("retain_range");
+        }
+    }
+
+    /// Applies a batch of inserts (`true`) and removes (`false`) sorted by
+    /// strictly ascending value. Splits the tree at the middle operation
+    /// and recurses on both halves, so m operations take
+    /// O(m log(n / m + 1)) instead of m separate descents.
+    ///
+    /// The batch emits no observer events at all: there are no per-value
+    /// `Inserted` or `Removed` events, and no `Rotated` events either,
+    /// since splitting and joining restructure the tree without the
+    /// rotations of a single insert or remove.
+    pub(crate) fn apply_sorted(&mut self, ops: Vec<(V, bool)>) {
+        let mut ops: Vec<_> = ops.into_iter().map(Some).collect();
+        let root = self.root.take();
+        self.root = self.apply_batch(root, &mut ops);
+
+        if self.self_check_level() == SelfCheck::Full {
+            self.run_self_check("apply_sorted");
+        }
+    }
+
+    fn apply_batch(&mut self, link: Link<V>, ops: &mut [Option<(V, bool)>]) -> Link<V> {
+        let mid = ops.len() / 2;
+        let Some((value, insert)) = ops.get_mut(mid).and_then(Option::take) else {
+            return link;
+        };
+
+        let (less, equal, greater) = split(link, &value);
+        let (left_ops, right_ops) = ops.split_at_mut(mid);
+        let less = self.apply_batch(less, left_ops);
+        let greater = self.apply_batch(greater, &mut right_ops[1..]);
+
+        if insert {
+            let value = match equal {
+                Some(existing) if self.duplicate_policy() == DuplicatePolicy::KeepExisting => {
+                    existing
+                }
+                _ => value,
+            };
+            Some(join(less, self.alloc_node(value), greater))
+        } else {
+            match greater {
+                None => less,
+                Some(greater) => {
+                    let (min, rest) = take_min(greater);
+                    Some(join(less, min, rest))
+                }
+            }
+        }
+    }
+
+    /// Moves the values for which `pred` holds into the first tree and the
+    /// rest into the second, building both balanced in O(n).
+    pub fn partition<F: FnMut(&V) -> bool>(self, pred: F) -> (AvlTree<V>, AvlTree<V>) {
+        let (matching, rest): (Vec<V>, Vec<V>) = self.into_iter().partition(pred);
+        let (matching_len, rest_len) = (matching.len(), rest.len());
+        (
+            AvlTree::from_sorted(&mut matching.into_iter(), matching_len),
+            AvlTree::from_sorted(&mut rest.into_iter(), rest_len),
+        )
+    }
+}
+
+pub(crate) fn split<V, Q>(link: Link<V>, value: &Q) -> (Link<V>, Option<V>, Link<V>)
+where
+    V: Borrow<Q>,
+    Q: Ord + ?Sized,
+{
+    let Some(mut node) = link else {
+        return (None, None, None);
+    };
+
+    let (left, right) = (node.left.take(), node.right.take());
+    match value.cmp(node.val.borrow()) {
+        cmp::Ordering::Equal => (left, Some(node.val), right),
+        cmp::Ordering::Less => {
+            let (less, equal, greater) = split(left, value);
+            (less, equal, Some(join(greater, node, right)))
+        }
+        cmp::Ordering::Greater => {
+            let (less, equal, greater) = split(right, value);
+            (Some(join(left, node, less)), equal, greater)
+        }
+    }
+}
+
+/// Detaches the smallest node, rejoining the rest without notifying the
+/// observer.
+fn take_min<V>(mut node: Box<AvlTreeNode<V>>) -> (Box<AvlTreeNode<V>>, Link<V>) {
+    let right = node.right.take();
+    match node.left.take() {
+        None => (node, right),
+        Some(left) => {
+            let (min, rest) = take_min(left);
+            (min, Some(join(rest, node, right)))
+        }
+    }
+}
+
+/// Joins two trees and a detached middle node, where every value in `left`
+/// is less than the middle value and every value in `right` greater. Takes
+/// time proportional to the difference of the two heights.
+pub(crate) fn join<V>(
+    left: Link<V>,
+    mut middle: Box<AvlTreeNode<V>>,
+    right: Link<V>,
+) -> Box<AvlTreeNode<V>> {
+    let (left_height, right_height) = (get_height(&left), get_height(&right));
+
+    match (left, right) {
+        (Some(left), right) if left_height > right_height + 1 => join_right(left, middle, right),
+        (left, Some(right)) if right_height > left_height + 1 => join_left(left, middle, right),
+        (left, right) => {
+            middle.left = left;
+            middle.right = right;
+            middle.update_height();
+            middle
+        }
+    }
+}
+
+fn join_right<V>(
+    mut left: Box<AvlTreeNode<V>>,
+    middle: Box<AvlTreeNode<V>>,
+    right: Link<V>,
+) -> Box<AvlTreeNode<V>> {
+    let inner = left.right.take();
+    left.right = Some(match inner {
+        Some(inner) if inner.height > get_height(&right) + 1 => join_right(inner, middle, right),
+        inner => join(inner, middle, right),
+    });
+    left.update_height();
+    left.rebalance()
+}
+
+fn join_left<V>(
+    left: Link<V>,
+    middle: Box<AvlTreeNode<V>>,
+    mut right: Box<AvlTreeNode<V>>,
+) -> Box<AvlTreeNode<V>> {
+    let inner = right.left.take();
+    right.left = Some(match inner {
+        Some(inner) if inner.height > get_height(&left) + 1 => join_left(left, middle, inner),
+        inner => join(left, middle, inner),
+    });
+    right.update_height();
+    right.rebalance()
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::vec::Vec;
+    use std::sync::{Arc, Mutex};
+
+    use crate::{AvlTree, Event, Frame};
+
+    #[test]
+    fn retain_range_reports_every_dropped_value() {
+        let removed = Arc::new(Mutex::new(Vec::new()));
+        let mut tree = AvlTree::new();
+        for value in 0..10 {
+            tree.insert(value);
+        }
+        let sink = Arc::clone(&removed);
+        tree.set_observer(move |event: Event<'_, u32>| {
+            if let Event::Removed(&value) = event {
+                sink.lock().unwrap().push(value);
+            }
+        });
+        tree.start_recording();
+
+        tree.retain_range(3..7);
+
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), [3, 4, 5, 6]);
+        assert_eq!(*removed.lock().unwrap(), [0, 1, 2, 7, 8, 9]);
+        let frames = tree.stop_recording();
+        assert_eq!(frames.len(), 7);
+        assert!(matches!(frames.last(), Some(Frame::Snapshot(nodes)) if nodes.len() == 4));
+    }
+
+    #[test]
+    fn retain_range_handles_every_bound_kind() {
+        use core::ops::Bound::{Excluded, Included};
+
+        let mut tree = AvlTree::new();
+        for value in 0..10 {
+            tree.insert(value);
+        }
+        tree.retain_range((Excluded(2), Included(7)));
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), [3, 4, 5, 6, 7]);
+        assert_eq!(tree.check_invariants(), Ok(()));
+    }
+}
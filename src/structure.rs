@@ -0,0 +1,140 @@
+use alloc::vec::Vec;
+use core::{borrow::Borrow, cmp};
+
+use crate::{AvlTree, AvlTreeNode, Iter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// One node of [`AvlTree::structure`]. `parent` indexes into the same list
+/// and is `None` only for the root, as is `side`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeInfo<'a, V> {
+    pub value: &'a V,
+    pub depth: usize,
+    pub parent: Option<usize>,
+    pub side: Option<Side>,
+    pub height: usize,
+    pub balance: isize,
+}
+
+/// The part of a tree below one node, see [`AvlTree::subtree`].
+pub struct SubtreeView<'a, V> {
+    pub(crate) node: &'a AvlTreeNode<V>,
+}
+
+impl<V> Clone for SubtreeView<'_, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V> Copy for SubtreeView<'_, V> {}
+
+impl<'a, V: Ord> SubtreeView<'a, V> {
+    /// The value at the top of the subtree.
+    pub fn root(&self) -> &'a V {
+        &self.node.val
+    }
+
+    pub fn len(&self) -> usize {
+        self.node.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    pub fn height(&self) -> usize {
+        self.node.height as usize
+    }
+
+    pub fn balance(&self) -> isize {
+        self.node.get_balance()
+    }
+
+    pub fn min(&self) -> &'a V {
+        self.node.min()
+    }
+
+    pub fn max(&self) -> &'a V {
+        self.node.max()
+    }
+
+    pub fn iter(&self) -> Iter<'a, V> {
+        Iter::from_node(self.node)
+    }
+}
+
+impl<V: Ord> AvlTree<V> {
+    /// A view of the subtree whose top node holds `root_value`.
+    pub fn subtree<Q>(&self, root_value: &Q) -> Option<SubtreeView<'_, V>>
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut cur = &self.root;
+
+        while let Some(ref node) = cur {
+            match root_value.cmp(node.val.borrow()) {
+                cmp::Ordering::Less => cur = &node.left,
+                cmp::Ordering::Greater => cur = &node.right,
+                cmp::Ordering::Equal => return Some(SubtreeView { node }),
+            }
+        }
+
+        None
+    }
+
+    /// Lists every node in pre-order, so a parent always comes before its
+    /// children and a left subtree before the right one.
+    pub fn structure(&self) -> Vec<NodeInfo<'_, V>> {
+        let mut nodes = Vec::new();
+        let mut stack = Vec::new();
+        stack.extend(self.root.as_deref().map(|root| (root, None, 0)));
+
+        while let Some((node, parent, depth)) = stack.pop() {
+            let index = nodes.len();
+            nodes.push(NodeInfo {
+                value: &node.val,
+                depth,
+                parent: parent.map(|(parent, _)| parent),
+                side: parent.map(|(_, side)| side),
+                height: node.height as usize,
+                balance: node.get_balance(),
+            });
+
+            if let Some(ref right) = node.right {
+                stack.push((&**right, Some((index, Side::Right)), depth + 1));
+            }
+            if let Some(ref left) = node.left {
+                stack.push((&**left, Some((index, Side::Left)), depth + 1));
+            }
+        }
+
+        nodes
+    }
+
+    /// Whether both trees hold equal values in exactly the same
+    /// arrangement, not just the same set of values.
+    pub fn same_shape(&self, other: &AvlTree<V>) -> bool {
+        let mut stack = Vec::new();
+        stack.push((&self.root, &other.root));
+
+        while let Some(pair) = stack.pop() {
+            match pair {
+                (None, None) => (),
+                (Some(node), Some(other)) if node.val == other.val => {
+                    stack.push((&node.left, &other.left));
+                    stack.push((&node.right, &other.right));
+                }
+                _ => return false,
+            }
+        }
+
+        true
+    }
+}
@@ -0,0 +1,225 @@
+//! SVG and HTML export, enabled by the `svg` feature.
+//!
+//! The SVG layout gives every node its own column in sorted order and its
+//! own row by depth, so edges never cross and the picture is as wide as the
+//! tree has values. Nodes widen into ellipses to fit long values. Hovering
+//! a node shows its height and balance factor.
+
+use alloc::string::{String, ToString};
+use core::fmt::{self, Write};
+
+use crate::{render::display_width, AvlTree, Link};
+
+const MARGIN: usize = 24;
+const ROW_HEIGHT: usize = 64;
+const RADIUS: usize = 16;
+const CHAR_WIDTH: usize = 8;
+
+struct Placement {
+    column_width: usize,
+    radius_x: usize,
+    next_column: usize,
+    edges: String,
+    nodes: String,
+}
+
+impl<V: Ord + fmt::Display> AvlTree<V> {
+    pub fn to_svg(&self) -> String {
+        let mut widest = 0;
+        crate::walk(&self.root, &mut |value| {
+            widest = widest.max(display_width(value));
+        });
+
+        let mut placement = Placement {
+            column_width: (2 * RADIUS + 8).max(widest * CHAR_WIDTH + 20),
+            radius_x: RADIUS.max(widest * CHAR_WIDTH / 2 + 6),
+            next_column: 0,
+            edges: String::new(),
+            nodes: String::new(),
+        };
+        place(&self.root, 0, &mut placement).expect("writing to a String cannot fail");
+
+        let width = placement.next_column * placement.column_width + 2 * MARGIN;
+        let height = crate::get_height(&self.root) as usize * ROW_HEIGHT + 2 * MARGIN;
+        let mut out = String::new();
+        write_svg(&mut out, width, height, &placement).expect("writing to a String cannot fail");
+        out
+    }
+
+    /// A standalone HTML page listing the tree as nested, collapsible
+    /// `<details>` elements, left child first.
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+        write_html(&mut out, &self.root).expect("writing to a String cannot fail");
+        out
+    }
+}
+
+fn place<V: fmt::Display>(
+    link: &Link<V>,
+    depth: usize,
+    placement: &mut Placement,
+) -> Result<Option<(usize, usize)>, fmt::Error> {
+    let Some(node) = link else {
+        return Ok(None);
+    };
+
+    let left = place(&node.left, depth + 1, placement)?;
+    let x = MARGIN + placement.next_column * placement.column_width + placement.column_width / 2;
+    let y = MARGIN + depth * ROW_HEIGHT + ROW_HEIGHT / 2;
+    placement.next_column += 1;
+    let right = place(&node.right, depth + 1, placement)?;
+
+    for (child_x, child_y) in [left, right].into_iter().flatten() {
+        writeln!(
+            placement.edges,
+            r#"    <line x1="{}" y1="{}" x2="{}" y2="{}"/>"#,
+            x, y, child_x, child_y
+        )?;
+    }
+
+    writeln!(placement.nodes, "    <g>")?;
+    writeln!(
+        placement.nodes,
+        "      <title>h={}, b={}</title>",
+        node.height,
+        node.get_balance()
+    )?;
+    writeln!(
+        placement.nodes,
+        r#"      <ellipse cx="{}" cy="{}" rx="{}" ry="{}"/>"#,
+        x, y, placement.radius_x, RADIUS
+    )?;
+    write!(
+        placement.nodes,
+        r#"      <text x="{}" y="{}" fill="black" stroke="none" text-anchor="middle" dominant-baseline="central">"#,
+        x, y
+    )?;
+    write_escaped(&node.val.to_string(), &mut placement.nodes)?;
+    writeln!(placement.nodes, "</text>")?;
+    writeln!(placement.nodes, "    </g>")?;
+
+    Ok(Some((x, y)))
+}
+
+fn write_svg<W: Write>(
+    out: &mut W,
+    width: usize,
+    height: usize,
+    placement: &Placement,
+) -> fmt::Result {
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}" font-family="sans-serif" font-size="12">"#,
+        width, height
+    )?;
+    writeln!(out, r#"  <g stroke="black">"#)?;
+    out.write_str(&placement.edges)?;
+    writeln!(out, "  </g>")?;
+    writeln!(out, r#"  <g fill="white" stroke="black">"#)?;
+    out.write_str(&placement.nodes)?;
+    writeln!(out, "  </g>")?;
+    writeln!(out, "</svg>")
+}
+
+fn write_html<V: fmt::Display, W: Write>(out: &mut W, root: &Link<V>) -> fmt::Result {
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, r#"<meta charset="utf-8">"#)?;
+    writeln!(out, "<title>AVL tree</title>")?;
+    writeln!(
+        out,
+        "<style>details, .leaf {{ margin-left: 1.5em; font-family: monospace; }}</style>"
+    )?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    if root.is_some() {
+        write_html_node(out, root)?;
+    }
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")
+}
+
+fn write_html_node<V: fmt::Display, W: Write>(out: &mut W, link: &Link<V>) -> fmt::Result {
+    let Some(node) = link else {
+        return writeln!(out, r#"<div class="leaf">∅</div>"#);
+    };
+
+    let mut label = String::new();
+    write_escaped(&node.val.to_string(), &mut label)?;
+
+    if node.left.is_none() && node.right.is_none() {
+        return writeln!(out, r#"<div class="leaf">{}</div>"#, label);
+    }
+
+    writeln!(
+        out,
+        "<details open><summary>{} <small>(h={}, b={})</small></summary>",
+        label,
+        node.height,
+        node.get_balance()
+    )?;
+    write_html_node(out, &node.left)?;
+    write_html_node(out, &node.right)?;
+    writeln!(out, "</details>")
+}
+
+fn write_escaped<W: Write>(text: &str, out: &mut W) -> fmt::Result {
+    for c in text.chars() {
+        match c {
+            '&' => out.write_str("&amp;")?,
+            '<' => out.write_str("&lt;")?,
+            '>' => out.write_str("&gt;")?,
+            '"' => out.write_str("&quot;")?,
+            _ => out.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+
+    use crate::AvlTree;
+
+    #[test]
+    fn svg_output() {
+        let mut tree = AvlTree::new();
+        for value in [2, 1, 3] {
+            tree.insert(value);
+        }
+        let node = |x, y, title, value| {
+            [
+                String::from("    <g>"),
+                alloc::format!("      <title>{title}</title>"),
+                alloc::format!("      <ellipse cx=\"{x}\" cy=\"{y}\" rx=\"16\" ry=\"16\"/>"),
+                alloc::format!(
+                    "      <text x=\"{x}\" y=\"{y}\" fill=\"black\" stroke=\"none\" \
+                     text-anchor=\"middle\" dominant-baseline=\"central\">{value}</text>"
+                ),
+                String::from("    </g>"),
+            ]
+        };
+        let expected: String = [
+            String::from(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"168\" height=\"176\" \
+                 viewBox=\"0 0 168 176\" font-family=\"sans-serif\" font-size=\"12\">",
+            ),
+            String::from("  <g stroke=\"black\">"),
+            String::from("    <line x1=\"84\" y1=\"56\" x2=\"44\" y2=\"120\"/>"),
+            String::from("    <line x1=\"84\" y1=\"56\" x2=\"124\" y2=\"120\"/>"),
+            String::from("  </g>"),
+            String::from("  <g fill=\"white\" stroke=\"black\">"),
+        ]
+        .into_iter()
+        .chain(node(44, 120, "h=1, b=0", 1))
+        .chain(node(124, 120, "h=1, b=0", 3))
+        .chain(node(84, 56, "h=2, b=0", 2))
+        .chain([String::from("  </g>"), String::from("</svg>")])
+        .map(|line| line + "\n")
+        .collect();
+        assert_eq!(tree.to_svg(), expected);
+    }
+}
@@ -0,0 +1,406 @@
+//! Differential testing against `BTreeSet`, enabled by the `testing`
+//! feature.
+//!
+//! [`apply`] runs a sequence of [`Op`]s against an [`AvlTree`] and a
+//! `BTreeSet` shadow model side by side, and checks the tree's invariants
+//! after every step. The first step where the two disagree is returned as
+//! a [`Divergence`].
+//!
+//! For fuzzing, [`run`] decodes arbitrary bytes into operations with
+//! [`ops_from_bytes`] and panics on the first divergence, so a cargo-fuzz
+//! target only needs to call it:
+//!
+//! ```ignore
+//! fuzz_target!(|data: &[u8]| avl::testing::run(data));
+//! ```
+//!
+//! The panic message lists the operations up to the failing one as a
+//! `vec![...]` of `Op` expressions that can be pasted into a regression
+//! test.
+//!
+//! [`exhaustive`] complements random testing by replaying every short
+//! sequence of inserts and removes over a few values.
+//!
+//! For benchmarks, [`workload`] generates reproducible operation streams
+//! from a seed.
+
+use alloc::{collections::BTreeSet, vec::Vec};
+use core::{fmt, ops::Bound};
+
+use crate::{AvlTree, InvariantError};
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum Op<V> {
+    Insert(V),
+    Remove(V),
+    Contains(V),
+    Range(Bound<V>, Bound<V>),
+    Min,
+    Max,
+}
+
+/// Prints the operation as a Rust expression, e.g.
+/// `Op::Range(Bound::Included(3), Bound::Unbounded)`.
+impl<V: fmt::Debug> fmt::Debug for Op<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::Insert(value) => write!(f, "Op::Insert({:?})", value),
+            Op::Remove(value) => write!(f, "Op::Remove({:?})", value),
+            Op::Contains(value) => write!(f, "Op::Contains({:?})", value),
+            Op::Range(start, end) => {
+                write!(f, "Op::Range(")?;
+                write_bound(start, f)?;
+                write!(f, ", ")?;
+                write_bound(end, f)?;
+                write!(f, ")")
+            }
+            Op::Min => write!(f, "Op::Min"),
+            Op::Max => write!(f, "Op::Max"),
+        }
+    }
+}
+
+fn write_bound<V: fmt::Debug>(bound: &Bound<V>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match bound {
+        Bound::Included(value) => write!(f, "Bound::Included({:?})", value),
+        Bound::Excluded(value) => write!(f, "Bound::Excluded({:?})", value),
+        Bound::Unbounded => write!(f, "Bound::Unbounded"),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome<V> {
+    Bool(bool),
+    Value(Option<V>),
+    Values(Vec<V>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence<V> {
+    /// The tree and the model returned different results.
+    Mismatch {
+        step: usize,
+        op: Op<V>,
+        expected: Outcome<V>,
+        actual: Outcome<V>,
+    },
+    /// The results agreed but the tree's invariants no longer hold.
+    Invariant {
+        step: usize,
+        op: Op<V>,
+        error: InvariantError,
+    },
+}
+
+impl<V: fmt::Debug> fmt::Display for Divergence<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Divergence::Mismatch {
+                step,
+                op,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "step {}: {:?} returned {:?}, expected {:?}",
+                step, op, actual, expected
+            ),
+            Divergence::Invariant { step, op, error } => {
+                write!(f, "step {}: {:?} broke an invariant: {}", step, op, error)
+            }
+        }
+    }
+}
+
+pub fn apply<V: Ord + Clone>(ops: &[Op<V>]) -> Result<(), Divergence<V>> {
+    let mut tree = AvlTree::new();
+    let mut model = BTreeSet::new();
+
+    for (step, op) in ops.iter().enumerate() {
+        let (actual, expected) = match op {
+            Op::Insert(value) => (
+                Outcome::Bool(tree.insert(value.clone())),
+                Outcome::Bool(model.insert(value.clone())),
+            ),
+            Op::Remove(value) => (
+                Outcome::Bool(tree.remove(value)),
+                Outcome::Bool(model.remove(value)),
+            ),
+            Op::Contains(value) => (
+                Outcome::Bool(tree.contains(value)),
+                Outcome::Bool(model.contains(value)),
+            ),
+            Op::Range(start, end) => {
+                let bounds = (start.as_ref(), end.as_ref());
+                let expected = if is_empty_range(bounds) {
+                    Vec::new()
+                } else {
+                    model.range::<V, _>(bounds).cloned().collect()
+                };
+                (
+                    Outcome::Values(tree.range::<V, _>(bounds).cloned().collect()),
+                    Outcome::Values(expected),
+                )
+            }
+            Op::Min => (
+                Outcome::Value(tree.min().cloned()),
+                Outcome::Value(model.first().cloned()),
+            ),
+            Op::Max => (
+                Outcome::Value(tree.max().cloned()),
+                Outcome::Value(model.last().cloned()),
+            ),
+        };
+
+        if actual != expected {
+            return Err(Divergence::Mismatch {
+                step,
+                op: op.clone(),
+                expected,
+                actual,
+            });
+        }
+        if let Err(error) = tree.check_invariants() {
+            return Err(Divergence::Invariant {
+                step,
+                op: op.clone(),
+                error,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes every operation from one tag byte followed by its operands. Tags
+/// are taken modulo 8: 0 to 2 insert, 3 removes, 4 checks membership, 5 is
+/// a range, 6 and 7 query the minimum and maximum. A value is one byte, and
+/// a range reads a byte of bound kinds (two bits each for start and end:
+/// included, excluded or unbounded) before its two values. An operation cut
+/// short by the end of the input is dropped.
+pub fn ops_from_bytes(bytes: &[u8]) -> Vec<Op<u8>> {
+    let mut ops = Vec::new();
+    let mut bytes = bytes.iter().copied();
+
+    while let Some(tag) = bytes.next() {
+        let op = match tag % 8 {
+            0..=2 => bytes.next().map(Op::Insert),
+            3 => bytes.next().map(Op::Remove),
+            4 => bytes.next().map(Op::Contains),
+            5 => match (bytes.next(), bytes.next(), bytes.next()) {
+                (Some(kinds), Some(start), Some(end)) => Some(Op::Range(
+                    decode_bound(kinds, start),
+                    decode_bound(kinds >> 2, end),
+                )),
+                _ => None,
+            },
+            6 => Some(Op::Min),
+            _ => Some(Op::Max),
+        };
+
+        match op {
+            Some(op) => ops.push(op),
+            None => break,
+        }
+    }
+
+    ops
+}
+
+fn decode_bound(kind: u8, value: u8) -> Bound<u8> {
+    match kind % 4 {
+        0 | 1 => Bound::Included(value),
+        2 => Bound::Excluded(value),
+        _ => Bound::Unbounded,
+    }
+}
+
+/// Decodes `bytes` with [`ops_from_bytes`], replays them with [`apply`] and
+/// panics on the first divergence.
+pub fn run(bytes: &[u8]) {
+    let ops = ops_from_bytes(bytes);
+    if let Err(divergence) = apply(&ops) {
+        let step = match divergence {
+            Divergence::Mismatch { step, .. } | Divergence::Invariant { step, .. } => step,
+        };
+        panic!("{}\nops: {:?}", divergence, Ops(&ops[..=step]));
+    }
+}
+
+/// Replays every sequence of up to `n` operations, each inserting or
+/// removing one of `0..n`, with [`apply`]. Removes of absent values and
+/// repeated inserts are covered along with everything else. There are
+/// about (2n)^n sequences, so `n` beyond 6 is impractical.
+pub fn exhaustive(n: u8) -> Result<(), Divergence<u8>> {
+    for_each_sequence(n, apply)
+}
+
+/// Calls `f` with every sequence of `Op::Insert` and `Op::Remove` of
+/// `0..n` whose length is at most `n`, shortest first.
+fn for_each_sequence<E, F>(n: u8, mut f: F) -> Result<(), E>
+where
+    F: FnMut(&[Op<u8>]) -> Result<(), E>,
+{
+    let choices = 2 * n;
+    let op = |choice: u8| match choice.checked_sub(n) {
+        None => Op::Insert(choice),
+        Some(value) => Op::Remove(value),
+    };
+    let mut ops = Vec::with_capacity(n as usize);
+
+    for len in 0..=n as usize {
+        // Counts through every `len`-digit number in base `choices`.
+        let mut digits = alloc::vec![0; len];
+        loop {
+            ops.clear();
+            ops.extend(digits.iter().map(|&choice| op(choice)));
+            f(&ops)?;
+
+            let Some(i) = digits.iter().rposition(|&choice| choice + 1 < choices) else {
+                break;
+            };
+            digits[i] += 1;
+            digits[i + 1..].fill(0);
+        }
+    }
+
+    Ok(())
+}
+
+/// The shape of an operation stream generated by [`workload`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Workload {
+    /// Half inserts, a quarter each removes and lookups, of keys drawn
+    /// uniformly from `0..keys`.
+    Uniform { keys: u64 },
+    /// The same mix with keys from `0..keys` drawn by Zipf's law, where
+    /// key `i` is drawn in proportion to `1 / (i + 1)`.
+    Zipfian { keys: u64 },
+    /// Inserts in ascending order.
+    Sorted,
+    /// Inserts in ascending order, then removes smallest first, which
+    /// rebalances along the left spine on most removals.
+    AdversarialDeletes,
+}
+
+/// `len` operations of the given workload. The same seed always gives the
+/// same stream.
+pub fn workload(kind: Workload, len: usize, seed: u64) -> Vec<Op<u64>> {
+    let mut rng = SplitMix(seed);
+
+    match kind {
+        Workload::Uniform { keys } => {
+            let keys = keys.max(1);
+            mixed(len, &mut rng, |rng| rng.next() % keys)
+        }
+        Workload::Zipfian { keys } => {
+            let mut total = 0.0;
+            let cdf: Vec<f64> = (0..keys.max(1))
+                .map(|i| {
+                    total += 1.0 / (i + 1) as f64;
+                    total
+                })
+                .collect();
+            mixed(len, &mut rng, |rng| {
+                let target = rng.unit() * total;
+                cdf.partition_point(|&bound| bound < target) as u64
+            })
+        }
+        Workload::Sorted => (0..len as u64).map(Op::Insert).collect(),
+        Workload::AdversarialDeletes => {
+            let half = (len / 2) as u64;
+            (0..half)
+                .map(Op::Insert)
+                .chain((0..len as u64 - half).map(Op::Remove))
+                .collect()
+        }
+    }
+}
+
+fn mixed<F: FnMut(&mut SplitMix) -> u64>(
+    len: usize,
+    rng: &mut SplitMix,
+    mut key: F,
+) -> Vec<Op<u64>> {
+    (0..len)
+        .map(|_| match rng.next() % 4 {
+            0 | 1 => Op::Insert(key(rng)),
+            2 => Op::Remove(key(rng)),
+            _ => Op::Contains(key(rng)),
+        })
+        .collect()
+}
+
+/// SplitMix64, small and good enough for generating workloads.
+struct SplitMix(u64);
+
+impl SplitMix {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Panics with the violation if `tree` breaks an AVL invariant.
+pub fn assert_invariants<V: Ord>(tree: &AvlTree<V>) {
+    if let Err(error) = tree.check_invariants() {
+        panic!("AVL invariant broken: {}", error);
+    }
+}
+
+/// Debug-formats a slice of operations as a `vec![...]` expression.
+struct Ops<'a, V>(&'a [Op<V>]);
+
+impl<V: fmt::Debug> fmt::Debug for Ops<'_, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "vec!")?;
+        f.debug_list().entries(self.0).finish()
+    }
+}
+
+/// `BTreeSet::range` panics on these, the tree yields nothing.
+fn is_empty_range<V: Ord>(bounds: (Bound<&V>, Bound<&V>)) -> bool {
+    match bounds {
+        (Bound::Included(start), Bound::Included(end)) => start > end,
+        (Bound::Included(start), Bound::Excluded(end))
+        | (Bound::Excluded(start), Bound::Included(end))
+        | (Bound::Excluded(start), Bound::Excluded(end)) => start >= end,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{exhaustive, for_each_sequence, Op};
+
+    #[test]
+    fn sequences_cover_every_length_and_mix() {
+        let mut seen: Vec<Vec<Op<u8>>> = Vec::new();
+        for_each_sequence(2, |ops| {
+            seen.push(ops.to_vec());
+            Ok::<(), ()>(())
+        })
+        .unwrap();
+
+        assert_eq!(seen.len(), 1 + 4 + 16);
+        assert!(seen[0].is_empty());
+        assert!(seen.contains(&Vec::from([Op::Remove(1), Op::Insert(1)])));
+        assert!(seen.contains(&Vec::from([Op::Insert(0), Op::Insert(0)])));
+        assert!(seen.windows(2).all(|pair| pair[0].len() <= pair[1].len()));
+    }
+
+    #[test]
+    fn small_trees_match_the_model() {
+        assert_eq!(exhaustive(4), Ok(()));
+    }
+}
@@ -0,0 +1,96 @@
+use alloc::vec::Vec;
+
+use crate::AvlTree;
+
+/// Inserts and removals staged by [`AvlTree::transaction`].
+pub struct Transaction<'a, V> {
+    tree: &'a AvlTree<V>,
+    staged: Vec<Staged<V>>,
+}
+
+enum Staged<V> {
+    Insert(V),
+    Remove(V),
+}
+
+impl<V: Ord> AvlTree<V> {
+    /// Runs `f` with a [`Transaction`] and applies everything it staged if
+    /// `f` returns `Ok`. On `Err` the tree is left untouched.
+    pub fn transaction<R, E, F>(&mut self, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut Transaction<'_, V>) -> Result<R, E>,
+    {
+        let mut txn = Transaction {
+            tree: self,
+            staged: Vec::new(),
+        };
+        let result = f(&mut txn)?;
+
+        for staged in txn.staged {
+            match staged {
+                Staged::Insert(value) => {
+                    self.insert(value);
+                }
+                Staged::Remove(value) => {
+                    self.remove(&value);
+                }
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl<V: Ord> Transaction<'_, V> {
+    pub fn insert(&mut self, value: V) {
+        self.staged.push(Staged::Insert(value));
+    }
+
+    pub fn remove(&mut self, value: V) {
+        self.staged.push(Staged::Remove(value));
+    }
+
+    /// Whether `value` will be in the tree once the staged changes are
+    /// applied. Scans the staged changes, so it is linear in their number.
+    pub fn contains(&self, value: &V) -> bool {
+        let last = self.staged.iter().rev().find_map(|staged| match staged {
+            Staged::Insert(staged) => (staged == value).then_some(true),
+            Staged::Remove(staged) => (staged == value).then_some(false),
+        });
+        last.unwrap_or_else(|| self.tree.contains(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::AvlTree;
+
+    #[test]
+    fn err_rolls_back_everything_staged() {
+        let mut tree = AvlTree::new();
+        tree.insert(1);
+        tree.insert(2);
+
+        let result: Result<(), &str> = tree.transaction(|txn| {
+            txn.insert(3);
+            txn.remove(1);
+            assert!(txn.contains(&3));
+            assert!(!txn.contains(&1));
+            Err("abort")
+        });
+        assert_eq!(result, Err("abort"));
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), [1, 2]);
+
+        let result: Result<usize, ()> = tree.transaction(|txn| {
+            txn.insert(3);
+            txn.remove(1);
+            txn.insert(1);
+            txn.remove(2);
+            Ok(4)
+        });
+        assert_eq!(result, Ok(4));
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), [1, 3]);
+    }
+}
@@ -0,0 +1,176 @@
+//! Write-ahead logging on top of the [`BinaryCodec`] format.
+//!
+//! Every change to a [`LoggedAvlTree`] is appended to its log before it is
+//! applied. A record is one tag byte, `1` for an insert and `2` for a
+//! remove, followed by the encoded value. After a crash the tree is
+//! rebuilt from the latest snapshot written by
+//! [`LoggedAvlTree::checkpoint`] and the log written since, with
+//! [`AvlTree::read_from`] and [`AvlTree::replay`].
+
+use std::io::{self, Read, Write};
+
+use crate::{AvlTree, BinaryCodec};
+
+const INSERT: u8 = 1;
+const REMOVE: u8 = 2;
+
+/// An [`AvlTree`] that appends every insert and remove to a log.
+pub struct LoggedAvlTree<V, W> {
+    tree: AvlTree<V>,
+    log: W,
+}
+
+impl<V: Ord + BinaryCodec, W: Write> LoggedAvlTree<V, W> {
+    /// Logs changes from here on; `tree` should match the snapshot and log
+    /// it was recovered from.
+    pub fn new(tree: AvlTree<V>, log: W) -> LoggedAvlTree<V, W> {
+        LoggedAvlTree { tree, log }
+    }
+
+    pub fn tree(&self) -> &AvlTree<V> {
+        &self.tree
+    }
+
+    /// Applies nothing if the record cannot be written.
+    pub fn insert(&mut self, value: V) -> io::Result<()> {
+        self.append(INSERT, &value)?;
+        self.tree.insert(value);
+        Ok(())
+    }
+
+    /// Applies nothing if the record cannot be written.
+    pub fn remove(&mut self, value: &V) -> io::Result<bool> {
+        self.append(REMOVE, value)?;
+        Ok(self.tree.remove(value))
+    }
+
+    /// Encodes the whole record before writing it in one call, so a value
+    /// that fails to encode leaves no partial record in the log.
+    fn append(&mut self, tag: u8, value: &V) -> io::Result<()> {
+        let mut record = vec![tag];
+        value.encode(&mut record)?;
+        self.log.write_all(&record)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.log.flush()
+    }
+
+    /// Writes the whole tree to `snapshot` and continues logging to `log`,
+    /// returning the previous log, which the snapshot makes obsolete.
+    pub fn checkpoint<S: Write>(&mut self, snapshot: S, log: W) -> io::Result<W> {
+        self.log.flush()?;
+        self.tree.write_to(snapshot)?;
+        Ok(core::mem::replace(&mut self.log, log))
+    }
+
+    pub fn into_parts(self) -> (AvlTree<V>, W) {
+        (self.tree, self.log)
+    }
+}
+
+impl<V: Ord + BinaryCodec> AvlTree<V> {
+    /// Applies the records of a log written by [`LoggedAvlTree`]. A record
+    /// cut short at the end, as left by a crash during a write, is ignored.
+    pub fn replay<R: Read>(&mut self, mut reader: R) -> io::Result<()> {
+        loop {
+            let mut tag = [0];
+            if reader.read(&mut tag)? == 0 {
+                return Ok(());
+            }
+
+            let value = match V::decode(&mut reader) {
+                Ok(value) => value,
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(err) => return Err(err),
+            };
+            match tag[0] {
+                INSERT => {
+                    self.insert(value);
+                }
+                REMOVE => {
+                    self.remove(&value);
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "invalid log record",
+                    ))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read, Write};
+
+    use crate::{AvlTree, BinaryCodec, LoggedAvlTree};
+
+    /// Writes half of its encoding and then fails when odd.
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Flaky(u16);
+
+    impl BinaryCodec for Flaky {
+        fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+            writer.write_all(&self.0.to_le_bytes()[..1])?;
+            if self.0 % 2 == 1 {
+                return Err(io::Error::other("odd"));
+            }
+            writer.write_all(&self.0.to_le_bytes()[1..])
+        }
+
+        fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+            u16::decode(reader).map(Flaky)
+        }
+    }
+
+    #[test]
+    fn snapshot_and_log_recover_the_tree() {
+        let mut logged = LoggedAvlTree::new(AvlTree::new(), Vec::new());
+        for value in 0..10_u32 {
+            logged.insert(value).unwrap();
+        }
+        assert!(logged.remove(&3).unwrap());
+        assert!(!logged.remove(&30).unwrap());
+
+        let mut snapshot = Vec::new();
+        let old_log = logged.checkpoint(&mut snapshot, Vec::new()).unwrap();
+        logged.insert(42).unwrap();
+        logged.remove(&0).unwrap();
+        let (tree, log) = logged.into_parts();
+
+        let mut from_old_log = AvlTree::<u32>::new();
+        from_old_log.replay(&old_log[..]).unwrap();
+        assert!(from_old_log
+            .iter()
+            .eq(AvlTree::read_from(&snapshot[..]).unwrap().iter()));
+
+        let mut recovered = AvlTree::<u32>::read_from(&snapshot[..]).unwrap();
+        recovered.replay(&log[..]).unwrap();
+        assert!(recovered.iter().eq(tree.iter()));
+
+        // A crash mid-record leaves a torn tail, which replay skips.
+        let mut torn = AvlTree::<u32>::read_from(&snapshot[..]).unwrap();
+        torn.replay(&log[..log.len() - 2]).unwrap();
+        assert!(torn.contains(&42) && torn.contains(&0));
+    }
+
+    #[test]
+    fn failed_encoding_leaves_no_torn_record() {
+        let mut logged = LoggedAvlTree::new(AvlTree::new(), Vec::new());
+        logged.insert(Flaky(2)).unwrap();
+        assert!(logged.insert(Flaky(3)).is_err());
+        assert!(logged.remove(&Flaky(5)).is_err());
+        logged.insert(Flaky(4)).unwrap();
+
+        let (tree, log) = logged.into_parts();
+        assert_eq!(log.len(), 6);
+        assert_eq!(tree.len(), 2);
+
+        let mut replayed = AvlTree::<Flaky>::new();
+        replayed.replay(&log[..]).unwrap();
+        assert_eq!(replayed.iter().collect::<Vec<_>>(), [&Flaky(2), &Flaky(4)]);
+    }
+}